@@ -1,10 +1,14 @@
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
-use flate2::{Compression as GzCompression, write::GzEncoder, read::GzDecoder};
-use bzip2::{Compression as BzCompression, write::BzEncoder, read::BzDecoder};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use nao_dedup::io_compress;
 use clap::Parser;
 
+/// Compression level passed to `io_compress::open_writer` for the combined
+/// output. Not user-configurable here; callers who need a different level
+/// should compress the concatenated output separately.
+const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+
 // ------------------------------------------------------------------------------------------------
 // ARGUMENT PARSING
 // ------------------------------------------------------------------------------------------------
@@ -21,34 +25,18 @@ struct Args {
     output_file: String,
 }
 
-// Define a reader based on the file extension
+// Open a reader for `filename`, auto-detecting its compression (gzip/bgzip,
+// bzip2, zstd, xz, or plain text) from its magic bytes rather than its
+// extension, via the shared `io_compress` module.
 fn open_reader(filename: &str) -> std::io::Result<Box<dyn BufRead>> {
-    let file = File::open(filename)?;
-    if filename.ends_with(".gz") {
-        let decoder = GzDecoder::new(file);
-        Ok(Box::new(BufReader::new(decoder)))
-    } else if filename.ends_with(".bz2") {
-        let decoder = BzDecoder::new(file);
-        Ok(Box::new(BufReader::new(decoder)))
-    } else {
-        Ok(Box::new(BufReader::new(file)))
-    }
+    io_compress::open_reader(Path::new(filename))
 }
 
-// Define a writer based on the file extension
+// Open a writer for `filename`, selecting a codec from its extension
+// (`.gz`, `.bz2`, `.zst`, `.xz`; anything else is written uncompressed) via
+// the shared `io_compress` module.
 fn open_writer(filename: &str) -> std::io::Result<Box<dyn Write>> {
-    if filename.ends_with(".gz") {
-        let file = File::create(filename)?;
-        let encoder = GzEncoder::new(file, GzCompression::default());
-        Ok(Box::new(BufWriter::new(encoder)))
-    } else if filename.ends_with(".bz2") {
-        let file = File::create(filename)?;
-        let encoder = BzEncoder::new(file, BzCompression::default());
-        Ok(Box::new(BufWriter::new(encoder)))
-    } else {
-        let file = File::create(filename)?;
-        Ok(Box::new(BufWriter::new(file)))
-    }
+    io_compress::open_writer(Path::new(filename), DEFAULT_COMPRESSION_LEVEL)
 }
 
 fn read_header(reader: &mut dyn BufRead) -> io::Result<Option<Vec<String>>> {
@@ -1,23 +1,59 @@
 use clap::Parser;
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use nao_dedup::{DedupContext, DedupParams, MinimizerParams};
+use crossbeam_channel::bounded;
+use nao_dedup::io_compress;
+use nao_dedup::io_compress::{open_reader, open_writer};
+use nao_dedup::{
+    compute_minimizers, merge_pair, DedupContext, DedupParams, MinimizerParams, ReadPair,
+    DEFAULT_MAX_OVERLAP_HAMMING,
+};
+use rayon::prelude::*;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Write};
 use std::path::PathBuf;
+use std::thread;
+
+/// Number of read pairs the reader thread batches together before handing
+/// them to the worker pool. Large enough to amortize channel overhead, small
+/// enough (together with `CHANNEL_DEPTH`) to bound how much of the file can
+/// be buffered in memory ahead of the collector.
+const CHUNK_SIZE: usize = 5_000;
+
+/// Bounded channel depth between the reader thread and the collector, in
+/// chunks. Keeps memory bounded even if sketch computation falls behind I/O.
+const CHANNEL_DEPTH: usize = 4;
 
 #[derive(Parser)]
 #[command(name = "dedup_interleaved_fastq")]
-#[command(about = "Deduplicate interleaved paired-end FASTQ files", long_about = None)]
+#[command(about = "Deduplicate paired-end FASTQ files", long_about = None)]
 struct Cli {
-    /// Input FASTQ.gz file (interleaved R1/R2)
+    /// Input FASTQ.gz file (interleaved R1/R2). Mutually exclusive with
+    /// `--r1`/`--r2`.
     #[arg(value_name = "INPUT")]
-    input: PathBuf,
+    input: Option<PathBuf>,
 
-    /// Output FASTQ.gz file (exemplars only)
+    /// Output FASTQ.gz file (exemplars only, interleaved). Mutually
+    /// exclusive with `--out-r1`/`--out-r2`.
     #[arg(value_name = "OUTPUT")]
-    output: PathBuf,
+    output: Option<PathBuf>,
+
+    /// R1 FASTQ file, for separate R1/R2 input (requires `--r2`, mutually
+    /// exclusive with the positional interleaved INPUT)
+    #[arg(long)]
+    r1: Option<PathBuf>,
+
+    /// R2 FASTQ file, for separate R1/R2 input (requires `--r1`)
+    #[arg(long)]
+    r2: Option<PathBuf>,
+
+    /// Output path for R1 exemplars, for separate R1/R2 output (requires
+    /// `--out-r2`, mutually exclusive with the positional OUTPUT)
+    #[arg(long = "out-r1")]
+    out_r1: Option<PathBuf>,
+
+    /// Output path for R2 exemplars, for separate R1/R2 output (requires
+    /// `--out-r1`)
+    #[arg(long = "out-r2")]
+    out_r2: Option<PathBuf>,
 
     /// Maximum alignment offset (default: 1)
     #[arg(long, default_value_t = 1)]
@@ -38,9 +74,89 @@ struct Cli {
     /// Number of windows for minimizers (default: 4)
     #[arg(long, default_value_t = 4)]
     num_windows: usize,
+
+    /// Number of worker threads for minimizer sketching (default: number of CPUs)
+    #[arg(long, default_value_t = num_cpus::get())]
+    threads: usize,
+
+    /// Output compression level, on whatever scale the output extension's
+    /// codec uses (default: 6)
+    #[arg(long, default_value_t = 6)]
+    compression_level: u32,
+
+    /// Codec to use for stdout output (ignored for file output, which infers
+    /// its codec from the output path's extension)
+    #[arg(long, value_enum, default_value_t = StdoutCompression::None)]
+    stdout_compression: StdoutCompression,
+
+    /// Buffer pass-1 records in memory and write exemplars directly after
+    /// finalizing, instead of re-opening the input for pass 2. Implied when
+    /// reading from stdin (`-`), since stdin can't be re-read; optional
+    /// otherwise, trading memory for one fewer pass over the input.
+    #[arg(long)]
+    single_pass: bool,
+
+    /// Memory budget, in bytes, for the pass-1 record buffer in
+    /// `--single-pass` mode before spilling the remainder to a temp file
+    /// (default: 1 GiB)
+    #[arg(long, default_value_t = 1 << 30)]
+    max_buffer_bytes: usize,
+
+    /// Require well-formed, single-line FASTQ (bare `+` separator, one line
+    /// each for sequence and quality) and reject everything else. Without
+    /// this flag, input is parsed relaxed: the `+` line may repeat the
+    /// header, sequence/quality may wrap across multiple lines, and FASTA
+    /// input is accepted (with a synthesized quality string).
+    #[arg(long)]
+    strict: bool,
+
+    /// Merge overlapping mate pairs into a single fragment (see
+    /// `nao_dedup::merge_pair`) before deduping, instead of deduping on the
+    /// two mates independently. Pairs with no acceptable overlap fall back
+    /// to the normal unmerged path. Useful for short-insert libraries, where
+    /// duplicates that differ only in how far mate trimming ate into the
+    /// overlap would otherwise land in different clusters.
+    #[arg(long)]
+    merge_overlaps: bool,
+
+    /// Expected insert size for `--merge-overlaps`, used to derive the
+    /// overlap length directly (`fwd_len + rev_len - insert_size`). If
+    /// omitted, the overlap is auto-detected per pair by scanning for the
+    /// lowest-Hamming-distance alignment.
+    #[arg(long)]
+    insert_size: Option<usize>,
+
+    /// Maximum Hamming distance tolerated across the merge overlap for
+    /// `--merge-overlaps`; pairs whose best overlap exceeds this are left
+    /// unmerged rather than rejected outright.
+    #[arg(long, default_value_t = DEFAULT_MAX_OVERLAP_HAMMING)]
+    max_merge_hamming: usize,
+}
+
+/// Explicit output codec for stdout, which (unlike a file path) has no
+/// extension to infer one from.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StdoutCompression {
+    None,
+    Gz,
+    Bz2,
+    Zst,
+    Xz,
 }
 
-#[derive(Debug)]
+impl From<StdoutCompression> for io_compress::Codec {
+    fn from(codec: StdoutCompression) -> Self {
+        match codec {
+            StdoutCompression::None => io_compress::Codec::None,
+            StdoutCompression::Gz => io_compress::Codec::Gzip,
+            StdoutCompression::Bz2 => io_compress::Codec::Bzip2,
+            StdoutCompression::Zst => io_compress::Codec::Zstd,
+            StdoutCompression::Xz => io_compress::Codec::Xz,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 struct FastqRecord {
     header: String,
     sequence: String,
@@ -121,14 +237,231 @@ fn read_fastq_record<R: BufRead>(reader: &mut R) -> std::io::Result<Option<Fastq
     }))
 }
 
-/// Iterator that yields pairs of FASTQ records from an interleaved FASTQ file.
-struct FastqPairIterator<R: BufRead> {
+/// Quality value synthesized for FASTA input, which has no qualities of its
+/// own: Phred 40 ('I'), matching rust-bio-tools' convention for "treat this
+/// base as high-confidence".
+const SYNTHESIZED_QUAL_CHAR: char = 'I';
+
+/// Reads FASTQ and FASTA records under relaxed rules: the FASTQ `+`
+/// separator may repeat the header instead of being bare `+`, sequence and
+/// quality may each be wrapped across multiple lines, and FASTA records are
+/// accepted with a synthesized quality string. Used when `--strict` is not
+/// given, in place of `read_fastq_record`.
+struct RelaxedRecordReader<R: BufRead> {
     reader: R,
+    /// A line read to detect a record boundary but not yet consumed.
+    lookahead: Option<String>,
 }
 
-impl<R: BufRead> FastqPairIterator<R> {
+impl<R: BufRead> RelaxedRecordReader<R> {
     fn new(reader: R) -> Self {
-        Self { reader }
+        Self { reader, lookahead: None }
+    }
+
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        if let Some(line) = self.lookahead.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        line.truncate(line.trim_end().len());
+        Ok(Some(line))
+    }
+
+    fn read_record(&mut self) -> std::io::Result<Option<FastqRecord>> {
+        let header = loop {
+            match self.next_line()? {
+                None => return Ok(None),
+                Some(line) if line.is_empty() => continue, // tolerate blank lines between records
+                Some(line) => break line,
+            }
+        };
+
+        match header.chars().next() {
+            Some('@') => self.read_fastq_body(header).map(Some),
+            Some('>') => self.read_fasta_body(header).map(Some),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Expected FASTQ '@' or FASTA '>' header, got '{}'", header),
+            )),
+        }
+    }
+
+    fn read_fastq_body(&mut self, header: String) -> std::io::Result<FastqRecord> {
+        let mut sequence = String::new();
+        loop {
+            let Some(line) = self.next_line()? else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Incomplete FASTQ record: missing '+' separator",
+                ));
+            };
+            if line.starts_with('+') {
+                break;
+            }
+            sequence.push_str(&line);
+        }
+
+        // Wrapped quality is split across as many lines as the sequence
+        // was, so read until we've matched its length rather than reading a
+        // single line.
+        let mut quality = String::new();
+        while quality.len() < sequence.len() {
+            let Some(line) = self.next_line()? else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Incomplete FASTQ record: missing quality",
+                ));
+            };
+            quality.push_str(&line);
+        }
+
+        Ok(FastqRecord {
+            header,
+            sequence,
+            plus: "+".to_string(),
+            quality,
+        })
+    }
+
+    fn read_fasta_body(&mut self, header: String) -> std::io::Result<FastqRecord> {
+        let mut sequence = String::new();
+        loop {
+            match self.next_line()? {
+                Some(line) if line.starts_with('>') => {
+                    self.lookahead = Some(line);
+                    break;
+                }
+                Some(line) => sequence.push_str(&line),
+                None => break,
+            }
+        }
+
+        let quality = SYNTHESIZED_QUAL_CHAR.to_string().repeat(sequence.len());
+        Ok(FastqRecord {
+            header,
+            sequence,
+            plus: "+".to_string(),
+            quality,
+        })
+    }
+}
+
+/// Dispatches record reading to the strict single-line FASTQ parser or the
+/// relaxed multi-line/FASTA parser, so `FastqPairIterator`/
+/// `SeparatePairIterator` don't need to know which mode is active.
+enum RecordSource<R: BufRead> {
+    Strict(R),
+    Relaxed(RelaxedRecordReader<R>),
+}
+
+impl<R: BufRead> RecordSource<R> {
+    fn new(reader: R, strict: bool) -> Self {
+        if strict {
+            RecordSource::Strict(reader)
+        } else {
+            RecordSource::Relaxed(RelaxedRecordReader::new(reader))
+        }
+    }
+
+    fn read_record(&mut self) -> std::io::Result<Option<FastqRecord>> {
+        match self {
+            RecordSource::Strict(reader) => read_fastq_record(reader),
+            RecordSource::Relaxed(relaxed) => relaxed.read_record(),
+        }
+    }
+}
+
+/// Resolved input, after validating the CLI's mutually exclusive
+/// interleaved-vs-separate flags.
+#[derive(Clone)]
+enum InputMode {
+    Interleaved(PathBuf),
+    Paired(PathBuf, PathBuf),
+}
+
+/// Resolved output, mirroring `InputMode`.
+#[derive(Clone)]
+enum OutputMode {
+    Interleaved(PathBuf),
+    Paired(PathBuf, PathBuf),
+}
+
+impl InputMode {
+    fn from_cli(cli: &Cli) -> Result<Self, Box<dyn std::error::Error>> {
+        match (&cli.input, &cli.r1, &cli.r2) {
+            (Some(input), None, None) => Ok(InputMode::Interleaved(input.clone())),
+            (None, Some(r1), Some(r2)) => Ok(InputMode::Paired(r1.clone(), r2.clone())),
+            (None, Some(_), None) | (None, None, Some(_)) => {
+                Err("--r1 and --r2 must be given together".into())
+            }
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                Err("positional INPUT and --r1/--r2 are mutually exclusive".into())
+            }
+            (None, None, None) => Err("must supply INPUT or --r1/--r2".into()),
+        }
+    }
+}
+
+impl OutputMode {
+    fn from_cli(cli: &Cli) -> Result<Self, Box<dyn std::error::Error>> {
+        match (&cli.output, &cli.out_r1, &cli.out_r2) {
+            (Some(output), None, None) => Ok(OutputMode::Interleaved(output.clone())),
+            (None, Some(out_r1), Some(out_r2)) => {
+                Ok(OutputMode::Paired(out_r1.clone(), out_r2.clone()))
+            }
+            (None, Some(_), None) | (None, None, Some(_)) => {
+                Err("--out-r1 and --out-r2 must be given together".into())
+            }
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                Err("positional OUTPUT and --out-r1/--out-r2 are mutually exclusive".into())
+            }
+            (None, None, None) => Err("must supply OUTPUT or --out-r1/--out-r2".into()),
+        }
+    }
+}
+
+/// Strip a trailing `/1`/`/2` mate suffix, or a Casava `1:`/`2:` field (which
+/// follows the read name after a space), leaving the mate-independent read
+/// name for comparing R1/R2 headers.
+fn strip_mate_suffix(header: &str) -> &str {
+    if let Some(base) = header.strip_suffix("/1").or_else(|| header.strip_suffix("/2")) {
+        return base;
+    }
+    if let Some(space_idx) = header.find(' ') {
+        return &header[..space_idx];
+    }
+    header
+}
+
+/// Check that an R1/R2 header pair names the same read, ignoring the mate
+/// suffix/field.
+fn validate_mate_headers(r1_header: &str, r2_header: &str) -> std::io::Result<()> {
+    if strip_mate_suffix(r1_header) != strip_mate_suffix(r2_header) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "R1/R2 header mismatch: '{}' vs '{}'",
+                r1_header, r2_header
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Iterator that yields pairs of FASTQ (or, in relaxed mode, FASTA) records
+/// from an interleaved file.
+struct FastqPairIterator<R: BufRead> {
+    source: RecordSource<R>,
+}
+
+impl<R: BufRead> FastqPairIterator<R> {
+    fn new(reader: R, strict: bool) -> Self {
+        Self {
+            source: RecordSource::new(reader, strict),
+        }
     }
 }
 
@@ -137,14 +470,14 @@ impl<R: BufRead> Iterator for FastqPairIterator<R> {
 
     fn next(&mut self) -> Option<Self::Item> {
         // Read R1
-        let r1 = match read_fastq_record(&mut self.reader) {
+        let r1 = match self.source.read_record() {
             Ok(Some(record)) => record,
             Ok(None) => return None, // EOF
             Err(e) => return Some(Err(e)),
         };
 
         // Read R2
-        let r2 = match read_fastq_record(&mut self.reader) {
+        let r2 = match self.source.read_record() {
             Ok(Some(record)) => record,
             Ok(None) => {
                 eprintln!("Warning: Odd number of reads in file. Last read ignored.");
@@ -157,23 +490,242 @@ impl<R: BufRead> Iterator for FastqPairIterator<R> {
     }
 }
 
-/// Creates a FASTQ pair iterator from a gzipped file.
+/// Iterator that yields pairs of FASTQ (or, in relaxed mode, FASTA) records
+/// read from two separate R1/R2 files, validating that headers agree up to
+/// the mate suffix and erroring if one file ends before the other.
+struct SeparatePairIterator {
+    r1_source: RecordSource<Box<dyn BufRead>>,
+    r2_source: RecordSource<Box<dyn BufRead>>,
+}
+
+impl SeparatePairIterator {
+    fn new(r1_reader: Box<dyn BufRead>, r2_reader: Box<dyn BufRead>, strict: bool) -> Self {
+        Self {
+            r1_source: RecordSource::new(r1_reader, strict),
+            r2_source: RecordSource::new(r2_reader, strict),
+        }
+    }
+}
+
+impl Iterator for SeparatePairIterator {
+    type Item = std::io::Result<(FastqRecord, FastqRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let r1 = match self.r1_source.read_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                // R1 is exhausted - R2 must be too, or the files disagree on length.
+                return match self.r2_source.read_record() {
+                    Ok(Some(_)) => Some(Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "R1 file ended before R2 file",
+                    ))),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                };
+            }
+            Err(e) => return Some(Err(e)),
+        };
+
+        let r2 = match self.r2_source.read_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return Some(Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "R2 file ended before R1 file",
+                )));
+            }
+            Err(e) => return Some(Err(e)),
+        };
+
+        if let Err(e) = validate_mate_headers(&r1.header, &r2.header) {
+            return Some(Err(e));
+        }
+
+        Some(Ok((r1, r2)))
+    }
+}
+
+/// Creates a pair iterator for the resolved input mode, auto-detecting each
+/// stream's compression (gzip/bgzip, bzip2, zstd, xz, or plain text) from its
+/// magic bytes.
 fn create_pair_iterator(
-    path: &PathBuf,
-) -> std::io::Result<FastqPairIterator<BufReader<GzDecoder<File>>>> {
-    let input_file = File::open(path)?;
-    let gz_decoder = GzDecoder::new(input_file);
-    let reader = BufReader::new(gz_decoder);
-    Ok(FastqPairIterator::new(reader))
+    mode: &InputMode,
+    strict: bool,
+) -> std::io::Result<Box<dyn Iterator<Item = std::io::Result<(FastqRecord, FastqRecord)>>>> {
+    match mode {
+        InputMode::Interleaved(path) => {
+            let reader = open_reader(path)?;
+            Ok(Box::new(FastqPairIterator::new(reader, strict)))
+        }
+        InputMode::Paired(r1_path, r2_path) => {
+            let r1_reader = open_reader(r1_path)?;
+            let r2_reader = open_reader(r2_path)?;
+            Ok(Box::new(SeparatePairIterator::new(r1_reader, r2_reader, strict)))
+        }
+    }
+}
+
+/// A contiguous batch of read pairs read from the input, tagged with the
+/// index of its first pair so the collector can recover global ordering.
+struct PairChunk {
+    start_idx: usize,
+    pairs: Vec<(FastqRecord, FastqRecord)>,
+}
+
+/// Spawn a dedicated thread that pulls pairs from the input (interleaved or
+/// separate R1/R2) and
+/// pushes them to a bounded channel in fixed-size chunks. Keeping this on its
+/// own thread lets I/O proceed while the main thread's worker pool sketches
+/// the previous chunk.
+fn spawn_reader(
+    mode: InputMode,
+    strict: bool,
+) -> (
+    thread::JoinHandle<std::io::Result<()>>,
+    crossbeam_channel::Receiver<PairChunk>,
+) {
+    let (tx, rx) = bounded::<PairChunk>(CHANNEL_DEPTH);
+
+    let handle = thread::spawn(move || -> std::io::Result<()> {
+        let pair_iter = create_pair_iterator(&mode, strict)?;
+        let mut start_idx = 0;
+        let mut buf = Vec::with_capacity(CHUNK_SIZE);
+
+        for pair_result in pair_iter {
+            let (r1, r2) = pair_result?;
+            buf.push((r1, r2));
+
+            if buf.len() == CHUNK_SIZE {
+                let pairs = std::mem::replace(&mut buf, Vec::with_capacity(CHUNK_SIZE));
+                let n = pairs.len();
+                if tx.send(PairChunk { start_idx, pairs }).is_err() {
+                    break; // collector dropped, stop reading
+                }
+                start_idx += n;
+            }
+        }
+
+        if !buf.is_empty() {
+            let _ = tx.send(PairChunk { start_idx, pairs: buf });
+        }
+
+        Ok(())
+    });
+
+    (handle, rx)
+}
+
+/// Accumulates pass-1 pairs in memory for `--single-pass` mode, so pass 2 can
+/// write exemplars without re-opening the input (which isn't possible for
+/// stdin). Once `max_bytes` of (approximate) record size has been buffered,
+/// the whole buffer - and everything pushed after it - spills to a temp file
+/// instead, keeping memory use bounded for inputs larger than the budget.
+struct PairBuffer {
+    max_bytes: usize,
+    bytes_used: usize,
+    in_memory: Vec<(FastqRecord, FastqRecord)>,
+    spill: Option<(tempfile::NamedTempFile, BufWriter<File>)>,
+}
+
+impl PairBuffer {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            bytes_used: 0,
+            in_memory: Vec::new(),
+            spill: None,
+        }
+    }
+
+    fn push(&mut self, r1: FastqRecord, r2: FastqRecord) -> std::io::Result<()> {
+        if let Some((_, writer)) = self.spill.as_mut() {
+            r1.write_to(writer)?;
+            r2.write_to(writer)?;
+            return Ok(());
+        }
+
+        self.bytes_used += r1.sequence.len() + r1.quality.len() + r2.sequence.len() + r2.quality.len();
+        self.in_memory.push((r1, r2));
+
+        if self.bytes_used > self.max_bytes {
+            self.spill_to_disk()?;
+        }
+        Ok(())
+    }
+
+    fn spill_to_disk(&mut self) -> std::io::Result<()> {
+        eprintln!(
+            "  Pass-1 buffer exceeded {} bytes; spilling the rest of pass 1 to a temp file",
+            self.max_bytes
+        );
+        let tmp = tempfile::NamedTempFile::new()?;
+        let mut writer = BufWriter::new(tmp.reopen()?);
+        for (r1, r2) in self.in_memory.drain(..) {
+            r1.write_to(&mut writer)?;
+            r2.write_to(&mut writer)?;
+        }
+        self.spill = Some((tmp, writer));
+        Ok(())
+    }
+
+    /// Consume the buffer, returning an iterator over all buffered pairs in
+    /// original order. If pairs were spilled, the returned iterator holds the
+    /// temp file open and removes it once dropped.
+    fn into_iter(
+        mut self,
+    ) -> std::io::Result<Box<dyn Iterator<Item = std::io::Result<(FastqRecord, FastqRecord)>>>>
+    {
+        match self.spill.take() {
+            None => Ok(Box::new(self.in_memory.into_iter().map(Ok))),
+            Some((tmp, mut writer)) => {
+                writer.flush()?;
+                let reader = BufReader::new(File::open(tmp.path())?);
+                // The spill file was re-serialized by `FastqRecord::write_to`
+                // as plain 4-line FASTQ, regardless of how the original
+                // input was parsed, so it's always read back strictly.
+                Ok(Box::new(SpillIterator {
+                    _tmp: tmp,
+                    inner: FastqPairIterator::new(reader, true),
+                }))
+            }
+        }
+    }
+}
+
+/// Wraps a `FastqPairIterator` reading back a spilled `PairBuffer`, holding
+/// the backing `NamedTempFile` alive (and deleting it on drop) for the
+/// iterator's lifetime.
+struct SpillIterator {
+    _tmp: tempfile::NamedTempFile,
+    inner: FastqPairIterator<BufReader<File>>,
+}
+
+impl Iterator for SpillIterator {
+    type Item = std::io::Result<(FastqRecord, FastqRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
+    let input_mode = InputMode::from_cli(&cli)?;
+    let output_mode = OutputMode::from_cli(&cli)?;
+
+    // Stdin can't be re-read for pass 2, so reading from it implies
+    // `--single-pass` regardless of whether the flag was passed.
+    let reads_from_stdin = matches!(&input_mode, InputMode::Interleaved(p) if io_compress::is_stdio(p))
+        || matches!(&input_mode, InputMode::Paired(r1, r2) if io_compress::is_stdio(r1) || io_compress::is_stdio(r2));
+    let single_pass = cli.single_pass || reads_from_stdin;
+
     // Set up parameters
     let dedup_params = DedupParams {
         max_offset: cli.max_offset,
         max_error_frac: cli.max_error_frac,
+        ..Default::default()
     };
 
     let minimizer_params = MinimizerParams {
@@ -182,32 +734,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         num_windows: cli.num_windows,
     };
 
-    eprintln!("Pass 1: Building deduplication index...");
+    eprintln!("Pass 1: Building deduplication index ({} threads)...", cli.threads);
 
-    // Pass 1: Read all pairs and build deduplication index
-    let pair_iter = create_pair_iterator(&cli.input)?;
+    // Size the global rayon pool once, up front; later stages (and any future
+    // parallel work) share it. Ignore the error if a pool was already built
+    // (e.g. under `cargo test`, which runs multiple binaries in one process).
+    let _ = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.threads)
+        .build_global();
 
-    let mut ctx = DedupContext::new(dedup_params, minimizer_params);
+    let mut ctx = DedupContext::new(dedup_params, minimizer_params.clone());
     let mut pair_count = 0;
 
-    for (idx, pair_result) in pair_iter.enumerate() {
-        let (r1, r2) = pair_result?;
+    // Pass 1: a dedicated reader thread pulls pairs into fixed-size chunks
+    // over a bounded channel; for each chunk we compute the minimizer
+    // sketches (the CPU-heavy, embarrassingly parallel stage) across the
+    // rayon pool, then feed results into `ctx` strictly in index order so
+    // cluster assignment - inherently sequential, since a read's exemplar
+    // depends on all prior reads - stays identical to the serial version.
+    let (reader_handle, chunk_rx) = spawn_reader(input_mode.clone(), cli.strict);
+
+    let mut pair_buffer = single_pass.then(|| PairBuffer::new(cli.max_buffer_bytes));
+
+    for chunk in chunk_rx {
+        if cli.merge_overlaps {
+            // Merging is cheap relative to minimizer sketching and isn't
+            // embarrassingly parallel the way sketch computation is (each
+            // merge result feeds straight into `ctx`'s sequential clustering
+            // via `process_merged_read`), so there's no precomputed-sketch
+            // stage to fan out over the rayon pool here.
+            for (offset, (r1, r2)) in chunk.pairs.into_iter().enumerate() {
+                let idx = chunk.start_idx + offset;
+                if let Some(buffer) = pair_buffer.as_mut() {
+                    buffer.push(r1.clone(), r2.clone())?;
+                }
+
+                let rp = ReadPair {
+                    read_id: idx.to_string(),
+                    fwd_seq: r1.sequence,
+                    rev_seq: r2.sequence,
+                    fwd_qual: r1.quality,
+                    rev_qual: r2.quality,
+                };
+                match merge_pair(&rp, cli.insert_size, cli.max_merge_hamming) {
+                    Some(merged) => {
+                        ctx.process_merged_read(idx, merged.seq, merged.qual);
+                    }
+                    None => {
+                        ctx.process_read_by_index(idx, rp.fwd_seq, rp.rev_seq, rp.fwd_qual, rp.rev_qual);
+                    }
+                }
+                pair_count = idx + 1;
+
+                if pair_count % 100_000 == 0 {
+                    eprintln!("  Processed {} read pairs...", pair_count);
+                }
+            }
+            continue;
+        }
 
-        // Process by index directly (more efficient than creating ReadPair with string ID)
-        ctx.process_read_by_index(
-            idx,
-            r1.sequence,
-            r2.sequence,
-            r1.quality,
-            r2.quality,
-        );
-        pair_count = idx + 1;
+        let sketches: Vec<Vec<u64>> = chunk
+            .pairs
+            .par_iter()
+            .map(|(r1, r2)| compute_minimizers(&r1.sequence, &r2.sequence, &minimizer_params))
+            .collect();
 
-        if pair_count % 100_000 == 0 {
-            eprintln!("  Processed {} read pairs...", pair_count);
+        for (offset, ((r1, r2), minimizers)) in chunk.pairs.into_iter().zip(sketches).enumerate() {
+            let idx = chunk.start_idx + offset;
+            if let Some(buffer) = pair_buffer.as_mut() {
+                buffer.push(r1.clone(), r2.clone())?;
+            }
+            ctx.process_read_by_index_with_minimizers(
+                idx,
+                r1.sequence,
+                r2.sequence,
+                r1.quality,
+                r2.quality,
+                &minimizers,
+            );
+            pair_count = idx + 1;
+
+            if pair_count % 100_000 == 0 {
+                eprintln!("  Processed {} read pairs...", pair_count);
+            }
         }
     }
 
+    reader_handle
+        .join()
+        .expect("reader thread panicked")?;
+
     eprintln!("  Total read pairs: {}", pair_count);
 
     // Finalize deduplication
@@ -232,31 +848,101 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     eprintln!("Pass 2: Writing exemplars to output...");
 
-    // Pass 2: Write exemplars
-    let pair_iter = create_pair_iterator(&cli.input)?;
+    // Pass 2: write exemplars, either from the pass-1 buffer (single-pass
+    // mode) or by re-opening the input.
+    let pair_iter = match pair_buffer {
+        Some(buffer) => buffer.into_iter()?,
+        None => create_pair_iterator(&input_mode, cli.strict)?,
+    };
+
+    // Interleaved output writes both mates to one stream; separate-file
+    // output keeps a dedicated writer per mate. Wrapped so the loop below
+    // doesn't need to know which mode is active.
+    enum PairWriter {
+        Interleaved(BufWriter<Box<dyn Write>>),
+        Paired(BufWriter<Box<dyn Write>>, BufWriter<Box<dyn Write>>),
+    }
+
+    impl PairWriter {
+        fn write_pair(&mut self, r1: &FastqRecord, r2: &FastqRecord) -> std::io::Result<()> {
+            match self {
+                PairWriter::Interleaved(w) => {
+                    r1.write_to(w)?;
+                    r2.write_to(w)?;
+                }
+                PairWriter::Paired(w1, w2) => {
+                    r1.write_to(w1)?;
+                    r2.write_to(w2)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    // Opens a writer for `path`, special-casing stdio: there's no extension
+    // to infer a codec from, so the codec comes from `--stdout-compression`
+    // instead, and we refuse to write a binary codec to an interactive
+    // terminal the way e.g. `gzip` does.
+    let open_output = |path: &PathBuf| -> Result<Box<dyn Write>, Box<dyn std::error::Error>> {
+        if io_compress::is_stdio(path) {
+            let codec: io_compress::Codec = cli.stdout_compression.into();
+            if codec.is_binary() && std::io::stdout().is_terminal() {
+                return Err(format!(
+                    "refusing to write {:?}-compressed output to a terminal; redirect stdout \
+                    to a file/pipe, or pass --stdout-compression none",
+                    codec
+                )
+                .into());
+            }
+            Ok(io_compress::wrap_writer(
+                Box::new(std::io::stdout()),
+                codec,
+                cli.compression_level,
+            )?)
+        } else {
+            Ok(open_writer(path, cli.compression_level)?)
+        }
+    };
 
-    let output_file = File::create(&cli.output)?;
-    let gz_encoder = GzEncoder::new(output_file, Compression::default());
-    let mut writer = BufWriter::new(gz_encoder);
+    let mut writer = match &output_mode {
+        OutputMode::Interleaved(path) => PairWriter::Interleaved(BufWriter::new(open_output(path)?)),
+        OutputMode::Paired(out_r1, out_r2) => PairWriter::Paired(
+            BufWriter::new(open_output(out_r1)?),
+            BufWriter::new(open_output(out_r2)?),
+        ),
+    };
 
     let mut written = 0;
+    let mut pass2_pair_count = 0;
 
     for (idx, pair_result) in pair_iter.enumerate() {
         let (r1, r2) = pair_result?;
 
         // Write if this is an exemplar
-        if exemplar_indices.contains(&idx) {
-            r1.write_to(&mut writer)?;
-            r2.write_to(&mut writer)?;
+        if exemplar_indices.contains(&(idx as u32)) {
+            writer.write_pair(&r1, &r2)?;
             written += 1;
         }
 
-        let current_index = idx + 1;
-        if current_index % 100_000 == 0 {
-            eprintln!("  Processed {} read pairs...", current_index);
+        pass2_pair_count = idx + 1;
+        if pass2_pair_count % 100_000 == 0 {
+            eprintln!("  Processed {} read pairs...", pass2_pair_count);
         }
     }
 
+    // Pass 1 and pass 2 must observe the same number of pairs: with
+    // concatenated-gzip input now read to EOF via `MultiGzDecoder`, a
+    // mismatch here means the input changed between passes (or something
+    // upstream is truncating one of the two reads), not a decoding artifact.
+    if pass2_pair_count != pair_count {
+        return Err(format!(
+            "Pass 1 and pass 2 saw different numbers of read pairs ({} vs {}); \
+            did the input file change between passes?",
+            pair_count, pass2_pair_count
+        )
+        .into());
+    }
+
     eprintln!("  Wrote {} exemplar pairs", written);
     eprintln!("Done!");
 
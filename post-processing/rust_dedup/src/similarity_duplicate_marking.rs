@@ -1,13 +1,158 @@
 use anyhow::{bail, Context, Result};
+use bio::io::fastq;
+use clap::Parser;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use nao_dedup::io_compress;
 use nao_dedup::{DedupContext, DedupParams, MinimizerParams, ReadPair};
-use std::env;
+use rust_htslib::bam;
+use rust_htslib::bam::record::Aux;
+use rust_htslib::bam::Read as BamRead;
+use rust_htslib::bgzf;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+/// Mark similarity-based duplicates on top of alignment-based dedup.
+///
+/// Three input modes are supported: the original alignment TSV (which
+/// already has a `prim_align_dup_exemplar` column from an upstream alignment
+/// stage), a standalone pair of FASTQ files when no alignment stage has run,
+/// or a name-sorted BAM to mark and re-emit in place.
+#[derive(Parser)]
+#[command(name = "similarity_duplicate_marking")]
+#[command(about = "Mark similarity-based duplicate reads", long_about = None)]
+struct Cli {
+    /// Input alignment TSV (tsv.gz), with prim_align_dup_exemplar already
+    /// populated. Mutually exclusive with the other modes.
+    #[arg(value_name = "INPUT_TSV")]
+    input_tsv: Option<PathBuf>,
+
+    /// Output TSV (tsv.gz), with sim_dup_exemplar appended. Required (and
+    /// only valid) alongside INPUT_TSV.
+    #[arg(value_name = "OUTPUT_TSV")]
+    output_tsv: Option<PathBuf>,
+
+    /// R1 FASTQ(.gz) input, for standalone dedup with no alignment stage
+    /// (requires `--fastq2`, mutually exclusive with the other modes)
+    #[arg(long)]
+    fastq: Option<PathBuf>,
+
+    /// R2 FASTQ(.gz) input (requires `--fastq`)
+    #[arg(long)]
+    fastq2: Option<PathBuf>,
+
+    /// R1 exemplar output FASTQ(.gz) (requires `--out-fastq2`)
+    #[arg(long = "out-fastq")]
+    out_fastq: Option<PathBuf>,
+
+    /// R2 exemplar output FASTQ(.gz) (requires `--out-fastq`)
+    #[arg(long = "out-fastq2")]
+    out_fastq2: Option<PathBuf>,
+
+    /// Name-sorted input BAM, for marking duplicates directly on alignment
+    /// records (requires `--out-bam`, mutually exclusive with the other modes)
+    #[arg(long)]
+    bam: Option<PathBuf>,
+
+    /// Output BAM, identical to the input except for the 0x400 duplicate
+    /// flag and `DI`/`DS` tags (requires `--bam`)
+    #[arg(long = "out-bam")]
+    out_bam: Option<PathBuf>,
+
+    /// Memory budget, in bytes, for the TSV-mode pass-1 row buffer before
+    /// spilling the remainder to a temp file (default: 1 GiB). Only applies
+    /// to TSV mode; the FASTQ and BAM modes stream each input once per pass
+    /// without needing a buffer.
+    #[arg(long, default_value_t = 1 << 30)]
+    max_buffer_bytes: usize,
+
+    /// Number of threads for BGZF (de)compression of the TSV input/output
+    /// (default: number of CPUs). Only applies to TSV mode; plain gzip
+    /// input is read single-threaded regardless, since it has no
+    /// independently (de)compressible blocks.
+    #[arg(long, default_value_t = num_cpus::get())]
+    threads: usize,
+
+    /// Maximum alignment offset for similarity matching (default: 1)
+    #[arg(long, default_value_t = 1)]
+    max_offset: usize,
+
+    /// Maximum error fraction for similarity matching (default: 0.01)
+    #[arg(long, default_value_t = 0.01)]
+    max_error_frac: f64,
+
+    /// K-mer length for minimizers (default: 15)
+    #[arg(long = "minimizer-k", default_value_t = 15)]
+    kmer_len: usize,
+
+    /// Window length for minimizers (default: 25)
+    #[arg(long = "minimizer-window", default_value_t = 25)]
+    window_len: usize,
+
+    /// Number of windows for minimizers (default: 4)
+    #[arg(long, default_value_t = 4)]
+    num_windows: usize,
+
+    /// Allow a match on either mate alone to merge a pair, instead of
+    /// requiring both mates to independently confirm similarity against a
+    /// candidate exemplar (the default).
+    #[arg(long)]
+    allow_single_mate_match: bool,
+
+    /// Optional secondary exact-match gate: a candidate that passes the
+    /// offset/error-rate similarity check is only merged if its mates are
+    /// also within this many edits (banded Needleman-Wunsch) of the new
+    /// read's mates. Unset (the default) skips this stage entirely.
+    #[arg(long)]
+    max_edits: Option<usize>,
+}
+
+/// Resolved, validated run mode.
+enum Mode {
+    Tsv { input: PathBuf, output: PathBuf },
+    Fastq { r1: PathBuf, r2: PathBuf, out_r1: PathBuf, out_r2: PathBuf },
+    Bam { input: PathBuf, output: PathBuf },
+}
+
+impl Mode {
+    fn from_cli(cli: &Cli) -> Result<Self> {
+        let tsv_given = cli.input_tsv.is_some() || cli.output_tsv.is_some();
+        let fastq_given =
+            cli.fastq.is_some() || cli.fastq2.is_some() || cli.out_fastq.is_some() || cli.out_fastq2.is_some();
+        let bam_given = cli.bam.is_some() || cli.out_bam.is_some();
+
+        match (tsv_given, fastq_given, bam_given) {
+            (false, false, false) => bail!(
+                "must supply INPUT_TSV/OUTPUT_TSV, --fastq/--fastq2/--out-fastq/--out-fastq2, \
+                or --bam/--out-bam"
+            ),
+            (true, false, false) => Ok(Mode::Tsv {
+                input: cli.input_tsv.clone().context("TSV mode requires INPUT_TSV")?,
+                output: cli.output_tsv.clone().context("TSV mode requires OUTPUT_TSV")?,
+            }),
+            (false, true, false) => Ok(Mode::Fastq {
+                r1: cli.fastq.clone().context("--fastq is required")?,
+                r2: cli.fastq2.clone().context("--fastq2 is required")?,
+                out_r1: cli.out_fastq.clone().context("--out-fastq is required")?,
+                out_r2: cli.out_fastq2.clone().context("--out-fastq2 is required")?,
+            }),
+            (false, false, true) => Ok(Mode::Bam {
+                input: cli.bam.clone().context("--bam is required")?,
+                output: cli.out_bam.clone().context("--out-bam is required")?,
+            }),
+            _ => bail!(
+                "TSV mode (INPUT_TSV/OUTPUT_TSV), FASTQ mode \
+                (--fastq/--fastq2/--out-fastq/--out-fastq2), and BAM mode \
+                (--bam/--out-bam) are mutually exclusive"
+            ),
+        }
+    }
+}
+
 fn find_column(header_fields: &[&str], name: &str) -> Result<usize> {
     header_fields
         .iter()
@@ -15,32 +160,190 @@ fn find_column(header_fields: &[&str], name: &str) -> Result<usize> {
         .with_context(|| format!("Missing required column: {}", name))
 }
 
-fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        bail!("Usage: {} <input.tsv.gz> <output.tsv.gz>", args[0]);
+/// Open `path` for reading, decompressing BGZF blocks across `threads`
+/// threads. htslib's bgzf reader transparently falls back to single-threaded
+/// plain-gzip decoding when `path` isn't actually BGZF-framed (i.e. has no
+/// independently decompressible blocks to parallelize), so this is always
+/// safe to call regardless of how the input was produced.
+fn open_tsv_reader(path: &Path, threads: usize) -> Result<BufReader<bgzf::Reader>> {
+    let mut reader = bgzf::Reader::from_path(path)
+        .with_context(|| format!("Cannot open input file: {}", path.display()))?;
+    if threads > 1 {
+        reader
+            .set_threads(threads)
+            .with_context(|| format!("Failed to set {} BGZF reader threads", threads))?;
+    }
+    Ok(BufReader::new(reader))
+}
+
+/// Open `path` for writing, compressing output as BGZF blocks in parallel
+/// across `threads` threads while preserving row order (blocks are still
+/// written to the file in the order they're filled).
+fn open_tsv_writer(path: &Path, threads: usize) -> Result<BufWriter<bgzf::Writer>> {
+    let mut writer = bgzf::Writer::from_path(path)
+        .with_context(|| format!("Cannot create output file: {}", path.display()))?;
+    if threads > 1 {
+        writer
+            .set_threads(threads)
+            .with_context(|| format!("Failed to set {} BGZF writer threads", threads))?;
+    }
+    Ok(BufWriter::new(writer))
+}
+
+/// One row from the input TSV, kept around for the output pass: its full
+/// original text, plus - if it came from an alignment-unique read - the
+/// `seq_id` to resolve against the finalized `DedupContext` once every read
+/// has been seen.
+struct BufferedRow {
+    line: String,
+    seq_id: Option<String>,
+}
+
+/// Accumulates pass-1 rows so pass 2 can write the `sim_dup_exemplar` column
+/// without re-opening (and re-decompressing) `input_path`. Once `max_bytes`
+/// of (approximate) row size has been buffered, the whole buffer - and
+/// everything pushed after it - spills to a gzipped temp file instead,
+/// keeping memory use bounded for inputs larger than the budget. Mirrors the
+/// `PairBuffer` single-pass buffering in `dedup_interleaved_fastq`.
+struct RowBuffer {
+    max_bytes: usize,
+    bytes_used: usize,
+    in_memory: Vec<BufferedRow>,
+    spill: Option<(tempfile::NamedTempFile, BufWriter<GzEncoder<File>>)>,
+}
+
+impl RowBuffer {
+    fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, bytes_used: 0, in_memory: Vec::new(), spill: None }
+    }
+
+    fn push(&mut self, row: BufferedRow) -> Result<()> {
+        if let Some((_, writer)) = self.spill.as_mut() {
+            Self::write_row(writer, &row)?;
+            return Ok(());
+        }
+
+        self.bytes_used += row.line.len();
+        self.in_memory.push(row);
+
+        if self.bytes_used > self.max_bytes {
+            self.spill_to_disk()?;
+        }
+        Ok(())
+    }
+
+    fn write_row<W: Write>(writer: &mut W, row: &BufferedRow) -> Result<()> {
+        match &row.seq_id {
+            Some(seq_id) => writeln!(writer, "U\t{}\t{}", seq_id, row.line)?,
+            None => writeln!(writer, "D\t{}", row.line)?,
+        }
+        Ok(())
+    }
+
+    fn spill_to_disk(&mut self) -> Result<()> {
+        eprintln!(
+            "  Pass-1 row buffer exceeded {} bytes; spilling the rest of pass 1 to a temp file",
+            self.max_bytes
+        );
+        let tmp = tempfile::NamedTempFile::new()?;
+        let mut writer = BufWriter::new(GzEncoder::new(tmp.reopen()?, Compression::default()));
+        for row in self.in_memory.drain(..) {
+            Self::write_row(&mut writer, &row)?;
+        }
+        self.spill = Some((tmp, writer));
+        Ok(())
+    }
+
+    fn parse_spilled(line: &str) -> Result<BufferedRow> {
+        if let Some(rest) = line.strip_prefix("D\t") {
+            return Ok(BufferedRow { line: rest.to_string(), seq_id: None });
+        }
+        if let Some(rest) = line.strip_prefix("U\t") {
+            let (seq_id, line) = rest.split_once('\t').context("Malformed spilled row")?;
+            return Ok(BufferedRow { line: line.to_string(), seq_id: Some(seq_id.to_string()) });
+        }
+        bail!("Malformed spilled row: {}", line);
     }
 
-    let input_path = &args[1];
-    let output_path = &args[2];
+    /// Consume the buffer, writing every row to `writer` in original order:
+    /// alignment duplicates as-is plus `NA`, alignment-unique rows plus the
+    /// exemplar `resolve` returns for their `seq_id`. Returns
+    /// `(n_prim_align_dups, n_sim_dups)`.
+    fn write_all<W: Write>(
+        self,
+        writer: &mut W,
+        mut resolve: impl FnMut(&str) -> String,
+    ) -> Result<(usize, usize)> {
+        let mut n_prim_align_dups = 0;
+        let mut n_sim_dups = 0;
+
+        let mut emit = |row: BufferedRow| -> Result<()> {
+            match row.seq_id {
+                None => {
+                    writeln!(writer, "{}\tNA", row.line.trim_end()).context("Failed to write line")?;
+                    n_prim_align_dups += 1;
+                }
+                Some(seq_id) => {
+                    let sim_exemplar = resolve(&seq_id);
+                    writeln!(writer, "{}\t{}", row.line.trim_end(), sim_exemplar)
+                        .context("Failed to write line")?;
+                    if sim_exemplar != seq_id {
+                        n_sim_dups += 1;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        match self.spill {
+            None => {
+                for row in self.in_memory {
+                    emit(row)?;
+                }
+            }
+            Some((tmp, mut spill_writer)) => {
+                spill_writer.flush().context("Failed to flush spill file")?;
+                let reader = BufReader::new(GzDecoder::new(File::open(tmp.path())?));
+                for line_result in reader.lines() {
+                    let line = line_result.context("Failed to read spilled row")?;
+                    emit(Self::parse_spilled(&line)?)?;
+                }
+            }
+        }
+
+        Ok((n_prim_align_dups, n_sim_dups))
+    }
+}
 
+/// Original TSV-based mode: a precomputed `prim_align_dup_exemplar` column
+/// marks alignment duplicates, and this adds a `sim_dup_exemplar` column for
+/// similarity duplicates among the alignment-unique reads. Rows are buffered
+/// from a single decompressing read of `input_path` and replayed from that
+/// buffer once `DedupContext` is finalized, rather than re-opening
+/// `input_path` for a second pass. Input/output (de)compression is BGZF,
+/// parallelized across `threads` threads.
+fn run_tsv_mode(
+    input_path: &Path,
+    output_path: &Path,
+    max_buffer_bytes: usize,
+    threads: usize,
+    dedup_params: DedupParams,
+    minimizer_params: MinimizerParams,
+) -> Result<()> {
     let start_time = Instant::now();
 
-    // Create deduplication context with default parameters
-    let dedup_params = DedupParams::default();
-    let minimizer_params = MinimizerParams::default();
     let mut ctx = DedupContext::new(dedup_params, minimizer_params);
 
-    // Pass 1: Process alignment-unique reads
     let mut n_reads = 0;
     let mut alignment_unique_count = 0;
+    let mut row_buffer = RowBuffer::new(max_buffer_bytes);
 
-    eprintln!("Running similarity-based deduplication on alignment-unique reads...");
+    eprintln!(
+        "Running similarity-based deduplication on alignment-unique reads ({} threads)...",
+        threads
+    );
 
-    let file = File::open(input_path)
-        .with_context(|| format!("Cannot open input file: {}", input_path))?;
-    let decoder = GzDecoder::new(file);
-    let reader = BufReader::new(decoder);
+    let reader = open_tsv_reader(input_path, threads)?;
     let mut lines = reader.lines();
 
     // Read header
@@ -73,7 +376,9 @@ fn main() -> Result<()> {
     .unwrap()
         + 1;
 
-    // Process reads
+    // Pass 1: cluster alignment-unique reads by similarity, buffering every
+    // row (duplicates verbatim, alignment-unique rows with their seq_id) so
+    // pass 2 can replay them without re-reading `input_path`.
     for line_result in lines {
         let line = line_result.context("Failed to read line")?;
         n_reads += 1;
@@ -93,6 +398,7 @@ fn main() -> Result<()> {
 
         // Only process alignment-unique reads
         if seq_id != prim_align_exemplar {
+            row_buffer.push(BufferedRow { line: line.clone(), seq_id: None })?;
             continue;
         }
 
@@ -105,8 +411,10 @@ fn main() -> Result<()> {
             fwd_qual: fields[query_qual_idx].to_string(),
             rev_qual: fields[query_qual_rev_idx].to_string(),
         };
+        let seq_id = seq_id.to_string();
 
         ctx.process_read(read_pair);
+        row_buffer.push(BufferedRow { line: line.clone(), seq_id: Some(seq_id) })?;
     }
 
     let (_total_processed, unique_clusters) = ctx.stats();
@@ -116,74 +424,289 @@ fn main() -> Result<()> {
     );
     eprintln!("Found {} unique sequence clusters", unique_clusters);
 
-    // Finalize Pass 1
+    // Finalize pass 1, then replay the buffer rather than re-reading input_path
     ctx.finalize();
 
-    // Pass 2: Write output with sim_dup_exemplar column
-    eprintln!("Pass 2: Writing output with sim_dup_exemplar column...");
+    eprintln!("Writing output with sim_dup_exemplar column...");
 
-    let file_in = File::open(input_path)
-        .with_context(|| format!("Cannot open input file: {}", input_path))?;
-    let decoder = GzDecoder::new(file_in);
-    let reader = BufReader::new(decoder);
-    let mut lines = reader.lines();
+    let mut writer = open_tsv_writer(output_path, threads)?;
 
-    let file_out = File::create(output_path)
-        .with_context(|| format!("Cannot create output file: {}", output_path))?;
-    let encoder = GzEncoder::new(file_out, Compression::default());
-    let mut writer = BufWriter::new(encoder);
-
-    // Skip header line and write stored header with new column
-    lines.next();
     writeln!(writer, "{}\tsim_dup_exemplar", header.trim_end())
         .context("Failed to write header")?;
 
-    let mut n_prim_align_dups = 0;
-    let mut n_sim_dups = 0;
+    let (n_prim_align_dups, n_sim_dups) =
+        row_buffer.write_all(&mut writer, |seq_id| ctx.get_cluster_id(seq_id))?;
 
-    // Process data rows
-    for (line_num, line_result) in lines.enumerate() {
-        let line = line_result.context("Failed to read line")?;
-        let fields: Vec<&str> = line.split('\t').collect();
+    writer.flush().context("Failed to flush output")?;
 
-        if fields.len() < min_fields {
-            bail!(
-                "Malformed line {}: expected at least {} fields, got {}",
-                line_num + 1,
-                min_fields,
-                fields.len()
-            );
+    let elapsed = start_time.elapsed();
+    eprintln!("Done!");
+    eprintln!(
+        "Marked similarity duplicates processing {} reads in {}s, of which {} \
+        were already non-exemplars via alignment and {} were additionally \
+        recognized as non-exemplars via similarity.",
+        n_reads, elapsed.as_secs(), n_prim_align_dups, n_sim_dups
+    );
+
+    Ok(())
+}
+
+/// Open a FASTQ reader for `path`, auto-detecting its compression (notably,
+/// unlike a single-member `GzDecoder`, this reads a bgzip/pigz multi-member
+/// `.fastq.gz` - the norm for sequencing data, and exactly what this series'
+/// own BGZF writer produces - to EOF instead of silently truncating to the
+/// first member).
+fn open_fastq(path: &Path) -> Result<fastq::Reader<Box<dyn BufRead>>> {
+    let reader = io_compress::open_reader(path)
+        .with_context(|| format!("Cannot open FASTQ file: {}", path.display()))?;
+    Ok(fastq::Reader::new(reader))
+}
+
+/// Standalone FASTQ mode: dedup a raw read-pair FASTQ with no alignment
+/// stage, writing only cluster exemplars back out (non-exemplars are
+/// dropped, not annotated, since there's no TSV row to annotate).
+fn run_fastq_mode(
+    r1_path: &Path,
+    r2_path: &Path,
+    out_r1_path: &Path,
+    out_r2_path: &Path,
+    dedup_params: DedupParams,
+    minimizer_params: MinimizerParams,
+) -> Result<()> {
+    let start_time = Instant::now();
+
+    let mut ctx = DedupContext::new(dedup_params, minimizer_params);
+
+    eprintln!("Running similarity-based deduplication on FASTQ read pairs...");
+
+    let mut n_reads = 0;
+    for pair in open_fastq(r1_path)?.records().zip(open_fastq(r2_path)?.records()) {
+        let (r1_record, r2_record) = pair;
+        let r1_record = r1_record.context("Failed to parse R1 FASTQ record")?;
+        let r2_record = r2_record.context("Failed to parse R2 FASTQ record")?;
+        n_reads += 1;
+
+        let read_pair = ReadPair {
+            read_id: r1_record.id().to_string(),
+            fwd_seq: String::from_utf8_lossy(r1_record.seq()).into_owned(),
+            rev_seq: String::from_utf8_lossy(r2_record.seq()).into_owned(),
+            fwd_qual: String::from_utf8_lossy(r1_record.qual()).into_owned(),
+            rev_qual: String::from_utf8_lossy(r2_record.qual()).into_owned(),
+        };
+
+        ctx.process_read(read_pair);
+    }
+
+    let (_total_processed, unique_clusters) = ctx.stats();
+    eprintln!("Processed {} read pairs", n_reads);
+    eprintln!("Found {} unique sequence clusters", unique_clusters);
+
+    ctx.finalize();
+
+    eprintln!("Pass 2: Writing exemplar FASTQ pairs...");
+
+    let mut out_r1 = fastq::Writer::new(GzEncoder::new(
+        File::create(out_r1_path).with_context(|| format!("Cannot create output file: {}", out_r1_path.display()))?,
+        Compression::default(),
+    ));
+    let mut out_r2 = fastq::Writer::new(GzEncoder::new(
+        File::create(out_r2_path).with_context(|| format!("Cannot create output file: {}", out_r2_path.display()))?,
+        Compression::default(),
+    ));
+
+    let mut written = 0;
+    for pair in open_fastq(r1_path)?.records().zip(open_fastq(r2_path)?.records()) {
+        let (r1_record, r2_record) = pair;
+        let r1_record = r1_record.context("Failed to parse R1 FASTQ record")?;
+        let r2_record = r2_record.context("Failed to parse R2 FASTQ record")?;
+
+        if ctx.get_cluster_id(r1_record.id()) == r1_record.id() {
+            out_r1.write_record(&r1_record).context("Failed to write R1 exemplar")?;
+            out_r2.write_record(&r2_record).context("Failed to write R2 exemplar")?;
+            written += 1;
         }
+    }
 
-        let seq_id = fields[seq_id_idx];
-        let prim_align_exemplar = fields[prim_align_idx];
+    out_r1.flush().context("Failed to flush R1 output")?;
+    out_r2.flush().context("Failed to flush R2 output")?;
 
-        if seq_id != prim_align_exemplar {
-            // Alignment duplicate - fast path
-            writeln!(writer, "{}\tNA", line.trim_end()).context("Failed to write line")?;
-            n_prim_align_dups += 1;
-        } else {
-            // Alignment-unique - query for similarity exemplar
-            let sim_exemplar = ctx.get_cluster_id(seq_id);
-            writeln!(writer, "{}\t{}", line.trim_end(), sim_exemplar)
-                .context("Failed to write line")?;
-
-            if sim_exemplar != seq_id {
-                n_sim_dups += 1;
+    let elapsed = start_time.elapsed();
+    eprintln!("Done!");
+    eprintln!(
+        "Wrote {} exemplar pairs out of {} read pairs in {}s",
+        written, n_reads, elapsed.as_secs()
+    );
+
+    Ok(())
+}
+
+/// BAM bases as an ASCII string (A/C/G/T/N, upper case).
+fn seq_string(record: &bam::Record) -> String {
+    String::from_utf8(record.seq().as_bytes()).expect("BAM sequence is not valid ASCII")
+}
+
+/// Phred quality substituted for BAM records with no quality information
+/// (`QUAL == "*"`): rust_htslib fills `record.qual()` with `0xFF` per base in
+/// that case, which is a valid BAM encoding but not a real Phred score -
+/// passing it straight through `+33` would panic in debug (`255u8 + 33`) and
+/// wrap to a bogus byte in release, which then underflows `mean_quality`'s
+/// `-33`. 30 is a reasonable placeholder (matches common "unknown quality"
+/// defaults) since there's no real score to fall back on.
+const MISSING_QUAL_PHRED: u8 = 30;
+
+/// BAM quality scores (raw Phred, no offset) as a Phred+33 ASCII string,
+/// matching the convention `ReadPair`'s quality fields use everywhere else.
+fn qual_string(record: &bam::Record) -> String {
+    record
+        .qual()
+        .iter()
+        .map(|&q| (if q == 0xFF { MISSING_QUAL_PHRED } else { q } + 33) as char)
+        .collect()
+}
+
+/// Order two consecutive name-grouped records as (R1, R2), erroring if they
+/// aren't one first-in-template and one last-in-template record.
+fn order_mates(a: bam::Record, b: bam::Record) -> Result<(bam::Record, bam::Record)> {
+    match (a.is_first_in_template(), b.is_first_in_template()) {
+        (true, false) => Ok((a, b)),
+        (false, true) => Ok((b, a)),
+        _ => bail!("expected one R1 and one R2 record per read pair in the name-sorted BAM"),
+    }
+}
+
+/// BAM mode: read a name-sorted, paired BAM, cluster by similarity the same
+/// way as the other modes, and re-emit every record with the 0x400
+/// (PCR/optical duplicate) flag set on non-exemplar pairs, plus `DI`
+/// (duplicate-set index) and `DS` (exemplar read name) tags on every primary
+/// record - mirroring samtools markdup's output conventions. Secondary and
+/// supplementary records are written through unchanged, without tags.
+fn run_bam_mode(
+    input_path: &Path,
+    output_path: &Path,
+    dedup_params: DedupParams,
+    minimizer_params: MinimizerParams,
+) -> Result<()> {
+    let start_time = Instant::now();
+
+    let mut ctx = DedupContext::new(dedup_params, minimizer_params);
+
+    eprintln!("Running similarity-based deduplication on BAM read pairs...");
+
+    let mut n_pairs = 0;
+    {
+        let mut reader = bam::Reader::from_path(input_path)
+            .with_context(|| format!("Cannot open BAM file: {}", input_path.display()))?;
+        let mut pending: Option<bam::Record> = None;
+        for result in reader.records() {
+            let record = result.context("Failed to read BAM record")?;
+            if record.is_secondary() || record.is_supplementary() {
+                continue;
+            }
+            match pending.take() {
+                None => pending = Some(record),
+                Some(first) => {
+                    let (r1, r2) = order_mates(first, record)?;
+                    n_pairs += 1;
+
+                    let read_pair = ReadPair {
+                        read_id: String::from_utf8_lossy(r1.qname()).into_owned(),
+                        fwd_seq: seq_string(&r1),
+                        rev_seq: seq_string(&r2),
+                        fwd_qual: qual_string(&r1),
+                        rev_qual: qual_string(&r2),
+                    };
+                    ctx.process_read(read_pair);
+                }
             }
         }
     }
 
-    writer.flush().context("Failed to flush output")?;
+    let (_total_processed, unique_clusters) = ctx.stats();
+    eprintln!("Processed {} read pairs", n_pairs);
+    eprintln!("Found {} unique sequence clusters", unique_clusters);
+
+    ctx.finalize();
+
+    eprintln!("Pass 2: writing BAM with duplicate flags...");
+
+    let mut reader = bam::Reader::from_path(input_path)
+        .with_context(|| format!("Cannot open BAM file: {}", input_path.display()))?;
+    let header = bam::Header::from_template(reader.header());
+    let mut writer = bam::Writer::from_path(output_path, &header, bam::Format::Bam)
+        .with_context(|| format!("Cannot create output BAM: {}", output_path.display()))?;
+
+    // Duplicate-set index, assigned in first-encountered order per exemplar
+    // read id (mirroring samtools markdup's `DI` tag).
+    let mut set_id_by_exemplar: HashMap<String, i32> = HashMap::new();
+    let mut next_set_id = 0i32;
+    let mut n_marked = 0;
+
+    let mut pending: Option<bam::Record> = None;
+    for result in reader.records() {
+        let record = result.context("Failed to read BAM record")?;
+        if record.is_secondary() || record.is_supplementary() {
+            writer.write(&record).context("Failed to write BAM record")?;
+            continue;
+        }
+
+        let Some(first) = pending.take() else {
+            pending = Some(record);
+            continue;
+        };
+
+        let (mut r1, mut r2) = order_mates(first, record)?;
+        let read_id = String::from_utf8_lossy(r1.qname()).into_owned();
+        let exemplar_id = ctx.get_cluster_id(&read_id);
+        let is_duplicate = exemplar_id != read_id;
+
+        let set_id = *set_id_by_exemplar.entry(exemplar_id.clone()).or_insert_with(|| {
+            let id = next_set_id;
+            next_set_id += 1;
+            id
+        });
+
+        for mate in [&mut r1, &mut r2] {
+            if is_duplicate {
+                mate.set_duplicate();
+                n_marked += 1;
+            } else {
+                mate.unset_duplicate();
+            }
+            mate.push_aux(b"DI", Aux::I32(set_id))?;
+            mate.push_aux(b"DS", Aux::String(&exemplar_id))?;
+            writer.write(mate).context("Failed to write BAM record")?;
+        }
+    }
 
     let elapsed = start_time.elapsed();
     eprintln!("Done!");
     eprintln!(
-        "Marked similarity duplicates processing {} reads in {}s, of which {} \
-        were already non-exemplars via alignment and {} were additionally \
-        recognized as non-exemplars via similarity.",
-        n_reads, elapsed.as_secs(), n_prim_align_dups, n_sim_dups
+        "Marked {} duplicate records out of {} read pairs in {}s",
+        n_marked, n_pairs, elapsed.as_secs()
     );
 
     Ok(())
 }
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let dedup_params = DedupParams {
+        max_offset: cli.max_offset,
+        max_error_frac: cli.max_error_frac,
+        require_both_mates: !cli.allow_single_mate_match,
+        max_edits: cli.max_edits,
+    };
+    let minimizer_params = MinimizerParams::new(cli.kmer_len, cli.window_len, cli.num_windows)
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    match Mode::from_cli(&cli)? {
+        Mode::Tsv { input, output } => {
+            run_tsv_mode(&input, &output, cli.max_buffer_bytes, cli.threads, dedup_params, minimizer_params)
+        }
+        Mode::Fastq { r1, r2, out_r1, out_r2 } => {
+            run_fastq_mode(&r1, &r2, &out_r1, &out_r2, dedup_params, minimizer_params)
+        }
+        Mode::Bam { input, output } => run_bam_mode(&input, &output, dedup_params, minimizer_params),
+    }
+}
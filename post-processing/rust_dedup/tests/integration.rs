@@ -0,0 +1,209 @@
+//! Integration tests for similarity_duplicate_marking
+//!
+//! These tests run the compiled binary on small fixture files and verify
+//! outputs. Record order and exemplar choice can legitimately vary between
+//! otherwise-equivalent dedup runs, so rather than asserting exact lines
+//! these compare the resulting cluster *partitions* (sets of `seq_id`s
+//! grouped by `sim_dup_exemplar`) - mirroring the sort-before-compare idiom
+//! rust-bio-tools' `compare_fastq` uses for FASTQ output.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Get path to the compiled binary
+fn binary_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_BIN_EXE_similarity_duplicate_marking"))
+}
+
+/// Get path to test fixtures directory
+fn fixtures_dir() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("fixtures");
+    path
+}
+
+/// Write `rows` (including the header, as the first element) as a gzipped
+/// TSV at `path`. Plain gzip, not BGZF - `open_tsv_reader` falls back to
+/// single-threaded plain-gzip decoding for inputs that aren't BGZF-framed.
+fn write_tsv_gz(path: &PathBuf, rows: &[String]) {
+    let file = File::create(path).expect("Failed to create input file");
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    for row in rows {
+        writeln!(encoder, "{}", row).expect("Failed to write row");
+    }
+    encoder.finish().expect("Failed to finish gzip encoding");
+}
+
+/// Read every line out of a BGZF-or-gzip TSV. The output is BGZF (a sequence
+/// of concatenated gzip members), so a plain single-member `GzDecoder`
+/// wouldn't see past the first block; `MultiGzDecoder` reads all of them.
+fn read_tsv_gz(path: &PathBuf) -> Vec<String> {
+    let file = File::open(path).expect("Failed to open output file");
+    let reader = BufReader::new(MultiGzDecoder::new(file));
+    reader.lines().map(|l| l.expect("Failed to read line")).collect()
+}
+
+/// Parse a dedup output TSV's lines (header first) and group `seq_id`s by
+/// their `sim_dup_exemplar`, returning the resulting clusters as sets of
+/// `seq_id`s. Grouping by set membership (rather than comparing the
+/// `seq_id -> exemplar` map directly) makes the result independent of row
+/// order and of which cluster member was chosen as exemplar.
+fn partition_by_sim_dup_exemplar(lines: &[String]) -> Vec<BTreeSet<String>> {
+    let header: Vec<&str> = lines[0].split('\t').collect();
+    let seq_id_idx = header.iter().position(|&f| f == "seq_id").expect("missing seq_id column");
+    let exemplar_idx = header
+        .iter()
+        .position(|&f| f == "sim_dup_exemplar")
+        .expect("missing sim_dup_exemplar column");
+
+    let mut groups: std::collections::BTreeMap<String, BTreeSet<String>> = std::collections::BTreeMap::new();
+    for line in &lines[1..] {
+        let fields: Vec<&str> = line.split('\t').collect();
+        groups
+            .entry(fields[exemplar_idx].to_string())
+            .or_default()
+            .insert(fields[seq_id_idx].to_string());
+    }
+
+    let mut partition: Vec<BTreeSet<String>> = groups.into_values().collect();
+    partition.sort();
+    partition
+}
+
+fn set(ids: &[&str]) -> BTreeSet<String> {
+    ids.iter().map(|s| s.to_string()).collect()
+}
+
+/// Three alignment-unique read pairs: `read_a` and `read_b` are near
+/// duplicates (one mismatch in the forward mate, identical reverse mate),
+/// and `read_c` shares no minimizers with either. `order` controls the row
+/// order the fixture is written in, so callers can check that the result
+/// doesn't depend on it.
+fn three_read_fixture(order: [&str; 3]) -> Vec<String> {
+    let qual40 = "I".repeat(40);
+
+    let rows_by_id = std::collections::HashMap::from([
+        (
+            "read_a",
+            format!(
+                "read_a\t{}\t{}\t{}\t{}\tread_a",
+                "ACGTACGTACGTACGTACGTACGTACGTACGTACGTACGT",
+                "TGCATGCATGCATGCATGCATGCATGCATGCATGCATGCA",
+                qual40,
+                qual40,
+            ),
+        ),
+        (
+            "read_b",
+            format!(
+                "read_b\t{}\t{}\t{}\t{}\tread_b",
+                "ACGTAGGTACGTACGTACGTACGTACGTACGTACGTACGT", // one mismatch vs. read_a's fwd_seq
+                "TGCATGCATGCATGCATGCATGCATGCATGCATGCATGCA",
+                qual40,
+                qual40,
+            ),
+        ),
+        (
+            "read_c",
+            format!(
+                "read_c\t{}\t{}\t{}\t{}\tread_c",
+                "TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTT",
+                "TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTT",
+                qual40,
+                qual40,
+            ),
+        ),
+    ]);
+
+    let mut rows = vec![
+        "seq_id\tquery_seq\tquery_seq_rev\tquery_qual\tquery_qual_rev\tprim_align_dup_exemplar".to_string(),
+    ];
+    rows.extend(order.iter().map(|id| rows_by_id[id].clone()));
+    rows
+}
+
+/// Run similarity_duplicate_marking in TSV mode over `input_gz`, with
+/// minimizer/similarity parameters tuned for these 40bp fixtures (the
+/// defaults assume much longer real reads), and return the decompressed
+/// output lines.
+fn run_tsv_mode(input_gz: &PathBuf, output_gz: &PathBuf) -> Vec<String> {
+    let output = Command::new(binary_path())
+        .args([
+            input_gz.to_str().unwrap(),
+            output_gz.to_str().unwrap(),
+            "--minimizer-k",
+            "8",
+            "--minimizer-window",
+            "20",
+            "--num-windows",
+            "2",
+            "--max-offset",
+            "0",
+            "--max-error-frac",
+            "0.05",
+        ])
+        .output()
+        .expect("Failed to execute binary");
+
+    assert!(
+        output.status.success(),
+        "Binary failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    read_tsv_gz(output_gz)
+}
+
+#[test]
+fn test_partition_matches_known_duplicates() {
+    let fixtures = fixtures_dir();
+    fs::create_dir_all(&fixtures).ok();
+    let input_gz = fixtures.join("partition_basic_input.tsv.gz");
+    let output_gz = fixtures.join("partition_basic_output.tsv.gz");
+
+    write_tsv_gz(&input_gz, &three_read_fixture(["read_a", "read_b", "read_c"]));
+    let lines = run_tsv_mode(&input_gz, &output_gz);
+
+    let partition = partition_by_sim_dup_exemplar(&lines);
+    let mut expected = vec![set(&["read_a", "read_b"]), set(&["read_c"])];
+    expected.sort();
+
+    assert_eq!(
+        partition, expected,
+        "near-duplicate read_a/read_b should cluster together, read_c alone"
+    );
+
+    fs::remove_file(&input_gz).ok();
+    fs::remove_file(&output_gz).ok();
+}
+
+#[test]
+fn test_partition_independent_of_row_order() {
+    let fixtures = fixtures_dir();
+    fs::create_dir_all(&fixtures).ok();
+    let input_gz = fixtures.join("partition_reordered_input.tsv.gz");
+    let output_gz = fixtures.join("partition_reordered_output.tsv.gz");
+
+    // Same three reads, written in a different order: the resulting
+    // partition should be unaffected, even though the specific exemplar
+    // chosen for the read_a/read_b cluster may differ from the other test.
+    write_tsv_gz(&input_gz, &three_read_fixture(["read_c", "read_b", "read_a"]));
+    let lines = run_tsv_mode(&input_gz, &output_gz);
+
+    let partition = partition_by_sim_dup_exemplar(&lines);
+    let mut expected = vec![set(&["read_a", "read_b"]), set(&["read_c"])];
+    expected.sort();
+
+    assert_eq!(partition, expected);
+
+    fs::remove_file(&input_gz).ok();
+    fs::remove_file(&output_gz).ok();
+}
@@ -5,12 +5,17 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
+use bio::io::{fasta, fastq};
 use clap::Parser;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
-use flate2::Compression;
+use flate2::Compression as GzCompression;
+use rust_htslib::bam;
+use rust_htslib::bam::header::HeaderRecord;
+use rust_htslib::bam::record::{Aux, CigarString};
 
 // ============================================================================
 // UC FORMAT COLUMN INDICES
@@ -51,49 +56,310 @@ struct Args {
     /// Column name prefix for output DB (default: no prefix)
     #[arg(short = 'p', long = "output-prefix", default_value = "")]
     output_prefix: String,
+
+    /// Output codec for the TSV output (default: auto-detected from
+    /// `output_db`'s extension: `.gz`, `.zst`, `.lz4`, otherwise none)
+    #[arg(long, value_enum)]
+    compression: Option<Codec>,
+
+    /// Use the original two-pass pipeline (decompresses the UC input twice,
+    /// but never holds the full row set in memory) instead of the default
+    /// single-pass one. Use this when the UC file is too large for its
+    /// parsed rows to fit in RAM.
+    #[arg(long)]
+    low_memory: bool,
+
+    /// Compute cluster sizes by summing each member's VSEARCH `;size=N`
+    /// abundance annotation (parsed from its own seq_id) instead of
+    /// counting one per member. Use when the input was clustered after
+    /// dereplication with `--sizein`.
+    #[arg(long)]
+    sizein: bool,
+
+    /// Append the cluster's final summed size as a `;size=N` suffix to
+    /// each representative ID written to `output_ids`, so the top-N file
+    /// can feed directly into another abundance-aware clustering step.
+    #[arg(long)]
+    sizeout: bool,
+
+    /// Gzipped FASTA/FASTQ file that was clustered, for `--output-fasta`
+    /// and/or `--output-sam`/`--output-bam` (requires at least one of them)
+    #[arg(long)]
+    sequences: Option<String>,
+
+    /// Output path for a FASTA of the top-N cluster representatives'
+    /// sequences, headers annotated with cluster ID and size (requires
+    /// `--sequences`)
+    #[arg(long)]
+    output_fasta: Option<String>,
+
+    /// Write every clustering hit as a SAM alignment against its cluster
+    /// representative (requires `--sequences`; mutually exclusive with
+    /// `--output-bam`)
+    #[arg(long)]
+    output_sam: Option<String>,
+
+    /// Write every clustering hit as a BAM alignment against its cluster
+    /// representative (requires `--sequences`; mutually exclusive with
+    /// `--output-sam`)
+    #[arg(long)]
+    output_bam: Option<String>,
+}
+
+/// Validated, mutually-exclusive choice between `--output-sam` and
+/// `--output-bam`.
+enum AlignmentOutput {
+    Sam(String),
+    Bam(String),
+}
+
+impl AlignmentOutput {
+    fn from_cli(
+        output_sam: &Option<String>,
+        output_bam: &Option<String>,
+    ) -> Result<Option<Self>, String> {
+        match (output_sam, output_bam) {
+            (Some(_), Some(_)) => {
+                Err("--output-sam and --output-bam are mutually exclusive".to_string())
+            }
+            (Some(path), None) => Ok(Some(AlignmentOutput::Sam(path.clone()))),
+            (None, Some(path)) => Ok(Some(AlignmentOutput::Bam(path.clone()))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+// ============================================================================
+// COMPRESSION
+// ============================================================================
+
+/// Codec for reading/writing the UC input and TSV output. Auto-detected from
+/// a path's extension unless overridden (for output) by `--compression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Codec {
+    Gzip,
+    Zstd,
+    Lz4,
+    None,
+}
+
+impl Codec {
+    fn from_extension(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Codec::Gzip,
+            Some("zst") => Codec::Zstd,
+            Some("lz4") => Codec::Lz4,
+            _ => Codec::None,
+        }
+    }
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
-/// Open a gzipped file for reading
-fn open_gz_reader(path: &str) -> Result<BufReader<GzDecoder<File>>, Box<dyn Error>> {
+/// Open `path` for reading, selecting a decoder from its extension (`.gz`,
+/// `.zst`, `.lz4`; anything else, including plain `.tsv`, is read as-is).
+fn open_reader(path: &str) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
     let file = File::open(path)?;
-    let decoder = GzDecoder::new(file);
-    Ok(BufReader::new(decoder))
+    let inner: Box<dyn Read> = match Codec::from_extension(path) {
+        Codec::Gzip => Box::new(GzDecoder::new(file)),
+        Codec::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        Codec::Lz4 => Box::new(lz4::Decoder::new(file)?),
+        Codec::None => Box::new(file),
+    };
+    Ok(Box::new(BufReader::new(inner)))
+}
+
+/// Wraps an `lz4::Encoder` so its frame end-mark gets written when the
+/// wrapper is dropped. Unlike zstd's `Encoder` (see `Codec::Zstd` below,
+/// which uses `.auto_finish()`), lz4's `Encoder` doesn't write its end-mark
+/// on `Drop` - without this, every `--compression lz4`/`.lz4` output would be
+/// a truncated frame that decoders reject.
+struct Lz4AutoFinish<W: Write> {
+    encoder: Option<lz4::Encoder<W>>,
+}
+
+impl<W: Write> Write for Lz4AutoFinish<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.as_mut().expect("write after finish").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.as_mut().expect("write after finish").flush()
+    }
 }
 
-/// Open a gzipped file for writing
-fn open_gz_writer(path: &str) -> Result<BufWriter<GzEncoder<File>>, Box<dyn Error>> {
+impl<W: Write> Drop for Lz4AutoFinish<W> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let (_, result) = encoder.finish();
+            if let Err(e) = result {
+                eprintln!("warning: failed to finish lz4 stream: {}", e);
+            }
+        }
+    }
+}
+
+/// Open `path` for writing, selecting an encoder from `codec` (falling back
+/// to the path's extension when `codec` is `None`, i.e. not overridden by
+/// `--compression`). Mixing codecs across input/output is fine - nothing
+/// here assumes the two agree.
+fn open_writer(path: &str, codec: Option<Codec>) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    let codec = codec.unwrap_or_else(|| Codec::from_extension(path));
     let file = File::create(path)?;
-    let encoder = GzEncoder::new(file, Compression::default());
-    Ok(BufWriter::new(encoder))
+    let inner: Box<dyn Write> = match codec {
+        Codec::Gzip => Box::new(GzEncoder::new(file, GzCompression::default())),
+        Codec::Zstd => Box::new(zstd::stream::write::Encoder::new(file, 0)?.auto_finish()),
+        Codec::Lz4 => Box::new(Lz4AutoFinish { encoder: Some(lz4::EncoderBuilder::new().build(file)?) }),
+        Codec::None => Box::new(file),
+    };
+    Ok(Box::new(BufWriter::new(inner)))
 }
 
 /// Format the TSV header with optional prefix
 fn format_header(prefix: &str) -> String {
     if prefix.is_empty() {
-        "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size".to_string()
+        "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size\tn_match\tn_mismatch\tn_insertion\tn_deletion\taln_length".to_string()
     } else {
         format!(
-            "seq_id\t{p}_cluster_id\t{p}_cluster_rep_id\t{p}_seq_length\t{p}_is_cluster_rep\t{p}_percent_identity\t{p}_orientation\t{p}_cigar\t{p}_cluster_size",
+            "seq_id\t{p}_cluster_id\t{p}_cluster_rep_id\t{p}_seq_length\t{p}_is_cluster_rep\t{p}_percent_identity\t{p}_orientation\t{p}_cigar\t{p}_cluster_size\t{p}_n_match\t{p}_n_mismatch\t{p}_n_insertion\t{p}_n_deletion\t{p}_aln_length",
             p = prefix
         )
     }
 }
 
+/// Reverse-complement a DNA sequence. Bases other than A/C/G/T (any case)
+/// pass through unchanged, so rare IUPAC ambiguity codes (and `N`) survive
+/// rather than being rejected.
+fn reverse_complement(seq: &str) -> String {
+    seq.bytes()
+        .rev()
+        .map(|b| match b {
+            b'A' => b'T',
+            b'a' => b't',
+            b'T' => b'A',
+            b't' => b'a',
+            b'C' => b'G',
+            b'c' => b'g',
+            b'G' => b'C',
+            b'g' => b'c',
+            other => other,
+        } as char)
+        .collect()
+}
+
+/// Per-record alignment statistics derived from a CIGAR string.
+struct CigarStats {
+    n_match: u64,
+    n_mismatch: u64,
+    n_insertion: u64,
+    n_deletion: u64,
+    aln_length: u64,
+}
+
+/// Parse a CIGAR string into alignment statistics, scanning token-by-token
+/// (a run of ASCII digits giving a length, followed by one operation
+/// character): `M`/`=` and `X` count as matches/mismatches, `I` as
+/// insertions, `D` as deletions; `aln_length` is the reference-consuming
+/// length (`M`/`=`/`X`/`D`). A literal `*` - including the value VSEARCH
+/// uses when it has no alignment detail to report - is treated as a fully-
+/// matching record: zero mismatches/indels, `aln_length` equal to
+/// `seq_length`.
+fn parse_cigar(cigar: &str, seq_length: u64) -> Result<CigarStats, String> {
+    if cigar == "*" {
+        return Ok(CigarStats {
+            n_match: seq_length,
+            n_mismatch: 0,
+            n_insertion: 0,
+            n_deletion: 0,
+            aln_length: seq_length,
+        });
+    }
+
+    let mut stats = CigarStats {
+        n_match: 0,
+        n_mismatch: 0,
+        n_insertion: 0,
+        n_deletion: 0,
+        aln_length: 0,
+    };
+    let mut len_digits = String::new();
+
+    for ch in cigar.chars() {
+        if ch.is_ascii_digit() {
+            len_digits.push(ch);
+            continue;
+        }
+
+        if len_digits.is_empty() {
+            return Err(format!("invalid CIGAR '{}': operation '{}' with no preceding length", cigar, ch));
+        }
+        let len: u64 = len_digits
+            .parse()
+            .map_err(|e| format!("invalid CIGAR '{}': {}", cigar, e))?;
+        len_digits.clear();
+
+        match ch {
+            'M' | '=' => {
+                stats.n_match += len;
+                stats.aln_length += len;
+            }
+            'X' => {
+                stats.n_mismatch += len;
+                stats.aln_length += len;
+            }
+            'I' => stats.n_insertion += len,
+            'D' => {
+                stats.n_deletion += len;
+                stats.aln_length += len;
+            }
+            other => return Err(format!("invalid CIGAR '{}': unknown operation '{}'", cigar, other)),
+        }
+    }
+
+    if !len_digits.is_empty() {
+        return Err(format!("invalid CIGAR '{}': trailing length with no operation", cigar));
+    }
+
+    Ok(stats)
+}
+
+/// Strip a VSEARCH-style `;size=N` abundance suffix from a sequence ID,
+/// returning the bare ID and the parsed weight (or a weight of `1` if
+/// there's no suffix). Used under `--sizein`/`--sizeout` so cluster
+/// abundance can be computed from, and re-annotated with, summed member
+/// sizes rather than raw record counts.
+fn strip_size_suffix(id: &str) -> Result<(String, u64), String> {
+    match id.rfind(";size=") {
+        Some(pos) => {
+            let size_str = &id[pos + ";size=".len()..];
+            let size: u64 = size_str
+                .parse()
+                .map_err(|e| format!("invalid ';size=' suffix in '{}': {}", id, e))?;
+            Ok((id[..pos].to_string(), size))
+        }
+        None => Ok((id.to_string(), 1)),
+    }
+}
+
 // ============================================================================
 // PASS 1: BUILD LOOKUP TABLES
 // ============================================================================
 
-/// Pass 1: Build cluster_sizes and cluster_reps lookup tables
+/// Pass 1: Build cluster_sizes and cluster_reps lookup tables.
+///
+/// Under `--sizein`, `cluster_sizes` is the sum of each member's
+/// `;size=N` abundance annotation (parsed from its own `seq_id`, for both
+/// `S` and `H` records) rather than the raw member count the `C` summary
+/// records give, so those are ignored.
 fn build_lookup_tables(
     input_path: &str,
+    sizein: bool,
 ) -> Result<(HashMap<u64, u64>, HashMap<u64, String>), Box<dyn Error>> {
     eprintln!("Pass 1: Building lookup tables...");
 
-    let reader = open_gz_reader(input_path)?;
+    let reader = open_reader(input_path)?;
     let mut cluster_sizes: HashMap<u64, u64> = HashMap::new();
     let mut cluster_reps: HashMap<u64, String> = HashMap::new();
 
@@ -117,14 +383,17 @@ fn build_lookup_tables(
 
         match record_type {
             "C" => {
-                // Cluster summary record: extract cluster_id and cluster_size
-                let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
-                    format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
-                })?;
-                let cluster_size: u64 = fields[SIZE].parse().map_err(|e| {
-                    format!("Line {}: invalid cluster_size '{}': {}", line_num, fields[SIZE], e)
-                })?;
-                cluster_sizes.insert(cluster_id, cluster_size);
+                // Cluster summary record: extract cluster_id and cluster_size,
+                // unless --sizein is summing abundance from S/H rows instead.
+                if !sizein {
+                    let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
+                        format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
+                    })?;
+                    let cluster_size: u64 = fields[SIZE].parse().map_err(|e| {
+                        format!("Line {}: invalid cluster_size '{}': {}", line_num, fields[SIZE], e)
+                    })?;
+                    cluster_sizes.insert(cluster_id, cluster_size);
+                }
             }
             "S" => {
                 // Seed (representative) record: extract cluster_id and representative seq_id
@@ -132,10 +401,23 @@ fn build_lookup_tables(
                     format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
                 })?;
                 let representative_id = fields[SEQ_ID].to_string();
+                if sizein {
+                    let (_, weight) = strip_size_suffix(&representative_id)
+                        .map_err(|e| format!("Line {}: {}", line_num, e))?;
+                    *cluster_sizes.entry(cluster_id).or_insert(0) += weight;
+                }
                 cluster_reps.insert(cluster_id, representative_id);
             }
             "H" => {
-                // Hit record: skip in pass 1
+                // Hit record: skip in pass 1, unless summing abundance.
+                if sizein {
+                    let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
+                        format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
+                    })?;
+                    let (_, weight) = strip_size_suffix(fields[SEQ_ID])
+                        .map_err(|e| format!("Line {}: {}", line_num, e))?;
+                    *cluster_sizes.entry(cluster_id).or_insert(0) += weight;
+                }
             }
             _ => {
                 return Err(format!(
@@ -164,13 +446,14 @@ fn build_lookup_tables(
 fn write_tsv_output(
     input_path: &str,
     output_path: &str,
+    output_codec: Option<Codec>,
     prefix: &str,
     cluster_sizes: &HashMap<u64, u64>,
 ) -> Result<(), Box<dyn Error>> {
     eprintln!("Pass 2: Writing TSV output...");
 
-    let reader = open_gz_reader(input_path)?;
-    let mut writer = open_gz_writer(output_path)?;
+    let reader = open_reader(input_path)?;
+    let mut writer = open_writer(output_path, output_codec)?;
 
     // Write header
     writeln!(writer, "{}", format_header(prefix))?;
@@ -203,7 +486,9 @@ fn write_tsv_output(
                     format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
                 })?;
                 let cluster_rep_id = fields[CLUSTER_REP_ID];
-                let seq_length = fields[SIZE];
+                let seq_length: u64 = fields[SIZE].parse().map_err(|e| {
+                    format!("Line {}: invalid seq_length '{}': {}", line_num, fields[SIZE], e)
+                })?;
                 let percent_identity = fields[PERCENT_ID];
                 let orientation = fields[ORIENTATION];
                 let cigar = fields[CIGAR];
@@ -212,10 +497,12 @@ fn write_tsv_output(
                 let cluster_size = cluster_sizes.get(&cluster_id).ok_or_else(|| {
                     format!("Line {}: cluster_id {} not found in lookup table", line_num, cluster_id)
                 })?;
+                let cigar_stats = parse_cigar(cigar, seq_length)
+                    .map_err(|e| format!("Line {}: {}", line_num, e))?;
 
                 writeln!(
                     writer,
-                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
                     seq_id,
                     cluster_id,
                     cluster_rep_id,
@@ -224,7 +511,12 @@ fn write_tsv_output(
                     percent_identity,
                     orientation,
                     cigar,
-                    cluster_size
+                    cluster_size,
+                    cigar_stats.n_match,
+                    cigar_stats.n_mismatch,
+                    cigar_stats.n_insertion,
+                    cigar_stats.n_deletion,
+                    cigar_stats.aln_length
                 )?;
                 records_written += 1;
             }
@@ -246,10 +538,12 @@ fn write_tsv_output(
                 let cluster_size = cluster_sizes.get(&cluster_id).ok_or_else(|| {
                     format!("Line {}: cluster_id {} not found in lookup table", line_num, cluster_id)
                 })?;
+                let cigar_stats = parse_cigar(&cigar, seq_length)
+                    .map_err(|e| format!("Line {}: {}", line_num, e))?;
 
                 writeln!(
                     writer,
-                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
                     seq_id,
                     cluster_id,
                     cluster_rep_id,
@@ -258,7 +552,12 @@ fn write_tsv_output(
                     percent_identity,
                     orientation,
                     cigar,
-                    cluster_size
+                    cluster_size,
+                    cigar_stats.n_match,
+                    cigar_stats.n_mismatch,
+                    cigar_stats.n_insertion,
+                    cigar_stats.n_deletion,
+                    cigar_stats.aln_length
                 )?;
                 records_written += 1;
             }
@@ -281,16 +580,192 @@ fn write_tsv_output(
     Ok(())
 }
 
+// ============================================================================
+// SINGLE-PASS PIPELINE (default; see `--low-memory` for the two-pass path)
+// ============================================================================
+
+/// A parsed `S`/`H` row, carrying everything needed to emit its TSV line
+/// once all rows (and therefore final cluster sizes) are known.
+struct ParsedRow {
+    seq_id: String,
+    cluster_id: u64,
+    cluster_rep_id: String,
+    seq_length: u64,
+    is_cluster_rep: bool,
+    percent_identity: String,
+    orientation: String,
+    cigar: String,
+}
+
+/// Stream the UC file once, parsing each `S`/`H` line into a `ParsedRow`
+/// kept in memory and counting cluster members as we go (seed = 1 plus its
+/// hits, or - under `--sizein` - each member's summed `;size=N` weight
+/// instead), so `C` summary records - read, but otherwise unused - are
+/// optional. Trades memory (the full row set, plus `cluster_sizes`/
+/// `cluster_reps`) for one fewer decompression pass over a multi-gigabyte
+/// input.
+fn parse_rows_single_pass(
+    input_path: &str,
+    sizein: bool,
+) -> Result<(Vec<ParsedRow>, HashMap<u64, u64>, HashMap<u64, String>), Box<dyn Error>> {
+    eprintln!("Single pass: parsing UC file...");
+
+    let reader = open_reader(input_path)?;
+    let mut rows = Vec::new();
+    let mut cluster_sizes: HashMap<u64, u64> = HashMap::new();
+    let mut cluster_reps: HashMap<u64, String> = HashMap::new();
+
+    let mut line_num = 0;
+    for line_result in reader.lines() {
+        line_num += 1;
+        let line = line_result?;
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        if fields.len() != UC_FIELD_COUNT {
+            return Err(format!(
+                "Line {}: expected {} fields, found {}",
+                line_num,
+                UC_FIELD_COUNT,
+                fields.len()
+            )
+            .into());
+        }
+
+        match fields[REC_TYPE] {
+            "S" => {
+                let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
+                    format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
+                })?;
+                let seq_id = fields[SEQ_ID].to_string();
+                let seq_length: u64 = fields[SIZE].parse().map_err(|e| {
+                    format!("Line {}: invalid seq_length '{}': {}", line_num, fields[SIZE], e)
+                })?;
+
+                let weight = if sizein {
+                    strip_size_suffix(&seq_id).map_err(|e| format!("Line {}: {}", line_num, e))?.1
+                } else {
+                    1
+                };
+                cluster_reps.insert(cluster_id, seq_id.clone());
+                *cluster_sizes.entry(cluster_id).or_insert(0) += weight;
+
+                rows.push(ParsedRow {
+                    cluster_rep_id: seq_id.clone(),
+                    seq_id,
+                    cluster_id,
+                    seq_length,
+                    is_cluster_rep: true,
+                    percent_identity: "100.0".to_string(),
+                    orientation: "+".to_string(),
+                    cigar: format!("{}M", seq_length),
+                });
+            }
+            "H" => {
+                let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
+                    format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
+                })?;
+                let seq_length: u64 = fields[SIZE].parse().map_err(|e| {
+                    format!("Line {}: invalid seq_length '{}': {}", line_num, fields[SIZE], e)
+                })?;
+
+                let weight = if sizein {
+                    strip_size_suffix(fields[SEQ_ID]).map_err(|e| format!("Line {}: {}", line_num, e))?.1
+                } else {
+                    1
+                };
+                *cluster_sizes.entry(cluster_id).or_insert(0) += weight;
+
+                rows.push(ParsedRow {
+                    seq_id: fields[SEQ_ID].to_string(),
+                    cluster_id,
+                    cluster_rep_id: fields[CLUSTER_REP_ID].to_string(),
+                    seq_length,
+                    is_cluster_rep: false,
+                    percent_identity: fields[PERCENT_ID].to_string(),
+                    orientation: fields[ORIENTATION].to_string(),
+                    cigar: fields[CIGAR].to_string(),
+                });
+            }
+            "C" => {
+                // Cluster summary record: no longer needed, since sizes are
+                // counted from S/H rows above.
+            }
+            other => {
+                return Err(format!("Line {}: unknown record type '{}'", line_num, other).into());
+            }
+        }
+    }
+
+    eprintln!(
+        "Single pass complete: {} rows, {} clusters",
+        rows.len(),
+        cluster_sizes.len()
+    );
+
+    Ok((rows, cluster_sizes, cluster_reps))
+}
+
+/// Emit the TSV output directly from rows already parsed by
+/// `parse_rows_single_pass`, looking up each row's final cluster size.
+fn write_tsv_output_from_rows(
+    output_path: &str,
+    output_codec: Option<Codec>,
+    prefix: &str,
+    rows: &[ParsedRow],
+    cluster_sizes: &HashMap<u64, u64>,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!("Writing TSV output...");
+
+    let mut writer = open_writer(output_path, output_codec)?;
+    writeln!(writer, "{}", format_header(prefix))?;
+
+    for row in rows {
+        let cluster_size = cluster_sizes.get(&row.cluster_id).ok_or_else(|| {
+            format!("cluster_id {} not found in lookup table", row.cluster_id)
+        })?;
+        let cigar_stats = parse_cigar(&row.cigar, row.seq_length)?;
+
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            row.seq_id,
+            row.cluster_id,
+            row.cluster_rep_id,
+            row.seq_length,
+            if row.is_cluster_rep { "True" } else { "False" },
+            row.percent_identity,
+            row.orientation,
+            row.cigar,
+            cluster_size,
+            cigar_stats.n_match,
+            cigar_stats.n_mismatch,
+            cigar_stats.n_insertion,
+            cigar_stats.n_deletion,
+            cigar_stats.aln_length,
+        )?;
+    }
+
+    writer.flush()?;
+    eprintln!("Wrote {} records", rows.len());
+
+    Ok(())
+}
+
 // ============================================================================
 // STEP 3: EXTRACT TOP N REPRESENTATIVE IDS
 // ============================================================================
 
-/// Extract top N representative IDs by cluster size
+/// Extract top N representative IDs by cluster size. Under `--sizeout`,
+/// each written ID has its cluster's final summed size re-annotated as a
+/// `;size=N` suffix (replacing any suffix it already carried), so the
+/// output can feed directly back into another abundance-aware clustering
+/// or chimera-detection step.
 fn write_top_representatives(
     output_path: &str,
     n_clusters: usize,
     cluster_sizes: &HashMap<u64, u64>,
     cluster_reps: &HashMap<u64, String>,
+    sizeout: bool,
 ) -> Result<(), Box<dyn Error>> {
     eprintln!("Step 3: Extracting top {} representative IDs...", n_clusters);
 
@@ -315,8 +790,13 @@ fn write_top_representatives(
     let mut writer = BufWriter::new(file);
 
     let n = std::cmp::min(n_clusters, clusters.len());
-    for i in 0..n {
-        writeln!(writer, "{}", clusters[i].1)?;
+    for &(size, rep_id) in clusters.iter().take(n) {
+        if sizeout {
+            let (base_id, _) = strip_size_suffix(rep_id)?;
+            writeln!(writer, "{};size={}", base_id, size)?;
+        } else {
+            writeln!(writer, "{}", rep_id)?;
+        }
     }
 
     writer.flush()?;
@@ -325,6 +805,204 @@ fn write_top_representatives(
     Ok(())
 }
 
+/// Read `sequences_path` (the FASTA/FASTQ file that was clustered) into a
+/// seq_id -> sequence map, auto-detecting FASTA vs FASTQ from its first
+/// record's leading byte (`>` vs `@`) and sharing `open_reader`'s
+/// compression auto-detection.
+fn load_sequences(sequences_path: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut reader = open_reader(sequences_path)?;
+    let first_byte = reader.fill_buf()?.first().copied();
+
+    let mut sequences = HashMap::new();
+    match first_byte {
+        Some(b'>') => {
+            for record in fasta::Reader::new(reader).records() {
+                let record = record?;
+                sequences.insert(record.id().to_string(), String::from_utf8(record.seq().to_vec())?);
+            }
+        }
+        Some(b'@') => {
+            for record in fastq::Reader::new(reader).records() {
+                let record = record?;
+                sequences.insert(record.id().to_string(), String::from_utf8(record.seq().to_vec())?);
+            }
+        }
+        Some(other) => {
+            return Err(format!(
+                "'{}' is neither FASTA nor FASTQ (expected '>' or '@', found byte {})",
+                sequences_path, other
+            )
+            .into());
+        }
+        None => {
+            return Err(format!("'{}' is empty", sequences_path).into());
+        }
+    }
+
+    Ok(sequences)
+}
+
+/// Write the top-N cluster representatives' sequences as FASTA, in
+/// size-descending order, with headers annotated by cluster ID and size
+/// (e.g. `>seq_001 cluster=0 size=297`).
+fn write_top_representatives_fasta(
+    output_path: &str,
+    n_clusters: usize,
+    cluster_sizes: &HashMap<u64, u64>,
+    cluster_reps: &HashMap<u64, String>,
+    sequences: &HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!("Writing top {} representative sequences as FASTA...", n_clusters);
+
+    let mut clusters: Vec<(u64, u64, &String)> = cluster_reps
+        .iter()
+        .filter_map(|(&cluster_id, rep_id)| {
+            cluster_sizes.get(&cluster_id).map(|&size| (size, cluster_id, rep_id))
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| match b.0.cmp(&a.0) {
+        std::cmp::Ordering::Equal => a.2.cmp(b.2),
+        other => other,
+    });
+
+    let file = File::create(output_path)?;
+    let mut writer = BufWriter::new(file);
+
+    let n = std::cmp::min(n_clusters, clusters.len());
+    for &(size, cluster_id, rep_id) in clusters.iter().take(n) {
+        let seq = sequences.get(rep_id).ok_or_else(|| {
+            format!("representative sequence '{}' not found in --sequences input", rep_id)
+        })?;
+        writeln!(writer, ">{} cluster={} size={}", rep_id, cluster_id, size)?;
+        writeln!(writer, "{}", seq)?;
+    }
+
+    writer.flush()?;
+    eprintln!("Wrote {} representative sequences", n);
+
+    Ok(())
+}
+
+/// Stream the UC file once more, emitting every `S`/`H` record as an
+/// alignment against its cluster representative: the representative
+/// becomes a reference sequence in the header (one `@SQ` per cluster, in
+/// `cluster_id` order), `H` records carry their VSEARCH CIGAR and
+/// `-`-orientation reverse-strand flag directly across, and `S` records
+/// become self-alignments (full-length `M` CIGAR, forward strand). The
+/// `NM` tag on both is the mismatch-plus-indel count from `parse_cigar`.
+fn write_alignments(
+    input_path: &str,
+    output_path: &str,
+    format: bam::Format,
+    cluster_reps: &HashMap<u64, String>,
+    sequences: &HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!("Writing alignments ({:?}) to {}...", format, output_path);
+
+    // Assign each cluster representative a stable tid, ordered by cluster_id.
+    let mut rep_order: Vec<(u64, &String)> = cluster_reps.iter().map(|(&id, rep)| (id, rep)).collect();
+    rep_order.sort_by_key(|&(cluster_id, _)| cluster_id);
+
+    let mut header = bam::Header::new();
+    let mut tid_by_cluster: HashMap<u64, i32> = HashMap::new();
+    for (tid, &(cluster_id, rep_id)) in rep_order.iter().enumerate() {
+        let rep_seq = sequences.get(rep_id).ok_or_else(|| {
+            format!("representative sequence '{}' not found in --sequences input", rep_id)
+        })?;
+        let mut sq_record = HeaderRecord::new(b"SQ");
+        sq_record.push_tag(b"SN", rep_id);
+        sq_record.push_tag(b"LN", rep_seq.len());
+        header.push_record(&sq_record);
+        tid_by_cluster.insert(cluster_id, tid as i32);
+    }
+
+    let mut writer = bam::Writer::from_path(output_path, &header, format)?;
+
+    let reader = open_reader(input_path)?;
+    let mut line_num = 0;
+    let mut records_written = 0;
+
+    for line_result in reader.lines() {
+        line_num += 1;
+        let line = line_result?;
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        if fields.len() != UC_FIELD_COUNT {
+            return Err(format!(
+                "Line {}: expected {} fields, found {}",
+                line_num,
+                UC_FIELD_COUNT,
+                fields.len()
+            )
+            .into());
+        }
+
+        let record_type = fields[REC_TYPE];
+        if record_type == "C" {
+            continue;
+        }
+        if record_type != "S" && record_type != "H" {
+            return Err(format!("Line {}: unknown record type '{}'", line_num, record_type).into());
+        }
+
+        let cluster_id: u64 = fields[CLUSTER_ID].parse().map_err(|e| {
+            format!("Line {}: invalid cluster_id '{}': {}", line_num, fields[CLUSTER_ID], e)
+        })?;
+        let seq_length: u64 = fields[SIZE].parse().map_err(|e| {
+            format!("Line {}: invalid seq_length '{}': {}", line_num, fields[SIZE], e)
+        })?;
+        let seq_id = fields[SEQ_ID];
+        let is_seed = record_type == "S";
+        let (cigar_str, reverse) = if is_seed {
+            (format!("{}M", seq_length), false)
+        } else {
+            (fields[CIGAR].to_string(), fields[ORIENTATION] == "-")
+        };
+
+        let tid = *tid_by_cluster.get(&cluster_id).ok_or_else(|| {
+            format!("Line {}: cluster_id {} has no representative", line_num, cluster_id)
+        })?;
+        let cigar_stats =
+            parse_cigar(&cigar_str, seq_length).map_err(|e| format!("Line {}: {}", line_num, e))?;
+        let seq_bases = sequences.get(seq_id).ok_or_else(|| {
+            format!("sequence '{}' not found in --sequences input", seq_id)
+        })?;
+        let cigar = CigarString::try_from(cigar_str.as_bytes())
+            .map_err(|e| format!("Line {}: invalid CIGAR '{}': {}", line_num, cigar_str, e))?;
+
+        // SAM requires SEQ in reference-forward orientation: for a
+        // reverse-strand record (FLAG 0x10, set below), that's the reverse
+        // complement of the read as VSEARCH reported it, not the read
+        // verbatim - otherwise SEQ disagrees with FLAG/CIGAR and samtools/IGV
+        // recompute mismatches (and NM) against the wrong bases.
+        let seq_for_record = if reverse { reverse_complement(seq_bases) } else { seq_bases.clone() };
+
+        let mut record = bam::Record::new();
+        record.set(
+            seq_id.as_bytes(),
+            Some(&cigar),
+            seq_for_record.as_bytes(),
+            &vec![255u8; seq_for_record.len()],
+        );
+        record.set_tid(tid);
+        record.set_pos(0);
+        record.set_mapq(255);
+        if reverse {
+            record.set_reverse();
+        }
+        let nm = cigar_stats.n_mismatch + cigar_stats.n_insertion + cigar_stats.n_deletion;
+        record.push_aux(b"NM", Aux::I32(nm as i32))?;
+
+        writer.write(&record)?;
+        records_written += 1;
+    }
+
+    eprintln!("Wrote {} alignment records", records_written);
+
+    Ok(())
+}
+
 // ============================================================================
 // MAIN
 // ============================================================================
@@ -339,16 +1017,30 @@ fn main() -> Result<(), Box<dyn Error>> {
     eprintln!("  N clusters: {}", args.n_clusters);
     eprintln!("  Prefix: {}", if args.output_prefix.is_empty() { "(none)" } else { &args.output_prefix });
 
-    // Pass 1: Build lookup tables
-    let (cluster_sizes, cluster_reps) = build_lookup_tables(&args.vsearch_db)?;
-
-    // Pass 2: Write TSV output
-    write_tsv_output(
-        &args.vsearch_db,
-        &args.output_db,
-        &args.output_prefix,
-        &cluster_sizes,
-    )?;
+    let (cluster_sizes, cluster_reps) = if args.low_memory {
+        // Pass 1: Build lookup tables
+        let (cluster_sizes, cluster_reps) = build_lookup_tables(&args.vsearch_db, args.sizein)?;
+        // Pass 2: Write TSV output
+        write_tsv_output(
+            &args.vsearch_db,
+            &args.output_db,
+            args.compression,
+            &args.output_prefix,
+            &cluster_sizes,
+        )?;
+        (cluster_sizes, cluster_reps)
+    } else {
+        let (rows, cluster_sizes, cluster_reps) =
+            parse_rows_single_pass(&args.vsearch_db, args.sizein)?;
+        write_tsv_output_from_rows(
+            &args.output_db,
+            args.compression,
+            &args.output_prefix,
+            &rows,
+            &cluster_sizes,
+        )?;
+        (cluster_sizes, cluster_reps)
+    };
 
     // Step 3: Write top N representative IDs
     write_top_representatives(
@@ -356,8 +1048,48 @@ fn main() -> Result<(), Box<dyn Error>> {
         args.n_clusters,
         &cluster_sizes,
         &cluster_reps,
+        args.sizeout,
     )?;
 
+    let alignment_output = AlignmentOutput::from_cli(&args.output_sam, &args.output_bam)?;
+
+    let sequences = match &args.sequences {
+        Some(sequences_path) => Some(load_sequences(sequences_path)?),
+        None => None,
+    };
+
+    match (&sequences, &args.output_fasta) {
+        (Some(sequences), Some(output_fasta_path)) => {
+            write_top_representatives_fasta(
+                output_fasta_path,
+                args.n_clusters,
+                &cluster_sizes,
+                &cluster_reps,
+                sequences,
+            )?;
+        }
+        (None, None) => {}
+        (None, Some(_)) => return Err("--output-fasta requires --sequences".into()),
+        (Some(_), None) if alignment_output.is_none() => {
+            return Err("--sequences requires --output-fasta or --output-sam/--output-bam".into())
+        }
+        (Some(_), None) => {}
+    }
+
+    if let Some(alignment_output) = alignment_output {
+        let sequences = sequences
+            .as_ref()
+            .ok_or("--output-sam/--output-bam require --sequences")?;
+        match alignment_output {
+            AlignmentOutput::Sam(path) => {
+                write_alignments(&args.vsearch_db, &path, bam::Format::Sam, &cluster_reps, sequences)?
+            }
+            AlignmentOutput::Bam(path) => {
+                write_alignments(&args.vsearch_db, &path, bam::Format::Bam, &cluster_reps, sequences)?
+            }
+        }
+    }
+
     eprintln!("Done.");
     Ok(())
 }
@@ -379,7 +1111,7 @@ mod tests {
         let header = format_header("");
         assert_eq!(
             header,
-            "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size"
+            "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size\tn_match\tn_mismatch\tn_insertion\tn_deletion\taln_length"
         );
     }
 
@@ -388,7 +1120,7 @@ mod tests {
         let header = format_header("vsearch");
         assert_eq!(
             header,
-            "seq_id\tvsearch_cluster_id\tvsearch_cluster_rep_id\tvsearch_seq_length\tvsearch_is_cluster_rep\tvsearch_percent_identity\tvsearch_orientation\tvsearch_cigar\tvsearch_cluster_size"
+            "seq_id\tvsearch_cluster_id\tvsearch_cluster_rep_id\tvsearch_seq_length\tvsearch_is_cluster_rep\tvsearch_percent_identity\tvsearch_orientation\tvsearch_cigar\tvsearch_cluster_size\tvsearch_n_match\tvsearch_n_mismatch\tvsearch_n_insertion\tvsearch_n_deletion\tvsearch_aln_length"
         );
     }
 
@@ -396,10 +1128,69 @@ mod tests {
     fn test_format_header_column_count() {
         let header = format_header("test");
         let columns: Vec<&str> = header.split('\t').collect();
-        assert_eq!(columns.len(), 9);
+        assert_eq!(columns.len(), 14);
         assert_eq!(columns[0], "seq_id"); // seq_id never gets prefix
     }
 
+    // -------------------------------------------------------------------------
+    // CIGAR parsing tests
+    // -------------------------------------------------------------------------
+
+    #[test]
+    fn test_parse_cigar_star_is_full_match() {
+        let stats = parse_cigar("*", 150).unwrap();
+        assert_eq!(stats.n_match, 150);
+        assert_eq!(stats.n_mismatch, 0);
+        assert_eq!(stats.n_insertion, 0);
+        assert_eq!(stats.n_deletion, 0);
+        assert_eq!(stats.aln_length, 150);
+    }
+
+    #[test]
+    fn test_parse_cigar_full_match() {
+        let stats = parse_cigar("297M", 297).unwrap();
+        assert_eq!(stats.n_match, 297);
+        assert_eq!(stats.aln_length, 297);
+    }
+
+    #[test]
+    fn test_parse_cigar_mixed_ops() {
+        let stats = parse_cigar("10M2I3D5X", 0).unwrap();
+        assert_eq!(stats.n_match, 10);
+        assert_eq!(stats.n_insertion, 2);
+        assert_eq!(stats.n_deletion, 3);
+        assert_eq!(stats.n_mismatch, 5);
+        assert_eq!(stats.aln_length, 10 + 3 + 5); // insertions don't consume the reference
+    }
+
+    #[test]
+    fn test_parse_cigar_invalid_op() {
+        assert!(parse_cigar("10Q", 0).is_err());
+    }
+
+    // -------------------------------------------------------------------------
+    // ;size= suffix tests
+    // -------------------------------------------------------------------------
+
+    #[test]
+    fn test_strip_size_suffix_present() {
+        let (base, weight) = strip_size_suffix("seq_001;size=42").unwrap();
+        assert_eq!(base, "seq_001");
+        assert_eq!(weight, 42);
+    }
+
+    #[test]
+    fn test_strip_size_suffix_absent() {
+        let (base, weight) = strip_size_suffix("seq_001").unwrap();
+        assert_eq!(base, "seq_001");
+        assert_eq!(weight, 1);
+    }
+
+    #[test]
+    fn test_strip_size_suffix_invalid() {
+        assert!(strip_size_suffix("seq_001;size=abc").is_err());
+    }
+
     // -------------------------------------------------------------------------
     // UC line parsing tests
     // -------------------------------------------------------------------------
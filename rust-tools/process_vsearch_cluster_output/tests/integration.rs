@@ -123,16 +123,16 @@ fn test_output_tsv_structure() {
     // Check header (no prefix)
     assert_eq!(
         lines[0],
-        "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size"
+        "seq_id\tcluster_id\tcluster_rep_id\tseq_length\tis_cluster_rep\tpercent_identity\torientation\tcigar\tcluster_size\tn_match\tn_mismatch\tn_insertion\tn_deletion\taln_length"
     );
 
     // Check row count: 3 S records + 3 H records = 6 data rows + 1 header = 7 lines
     assert_eq!(lines.len(), 7, "Expected 7 lines (1 header + 6 data rows)");
 
-    // Check each data row has 9 columns
+    // Check each data row has 14 columns (the original 9 plus the CIGAR-derived stats)
     for (i, line) in lines.iter().enumerate().skip(1) {
         let cols: Vec<&str> = line.split('\t').collect();
-        assert_eq!(cols.len(), 9, "Row {} has {} columns, expected 9", i, cols.len());
+        assert_eq!(cols.len(), 14, "Row {} has {} columns, expected 14", i, cols.len());
     }
 
     // Cleanup
@@ -0,0 +1,124 @@
+//! Shared input/output compression handling.
+//!
+//! On read, the codec is auto-detected by sniffing the first bytes of the
+//! stream rather than trusting the file extension, since pipelines commonly
+//! hand off files (or `bgzip`/`cat`-concatenated streams) whose name doesn't
+//! match their actual encoding. On write, the codec is selected by output
+//! extension. Used by both `dedup_interleaved_fastq` and the TSV
+//! concatenator so neither has to guess a file's codec on its own.
+
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression as BzCompression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression as GzCompression;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+use zstd::stream::write::Encoder as ZstdEncoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const BZIP2_MAGIC: [u8; 3] = [0x42, 0x5A, 0x68]; // "BZh"
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const XZ_MAGIC: [u8; 5] = [0xFD, 0x37, 0x7A, 0x58, 0x5A];
+
+/// Path spelling that callers/CLIs use to mean "read stdin"/"write stdout"
+/// instead of a real file, matching common Unix tool convention.
+pub const STDIO_PATH: &str = "-";
+
+/// Whether `path` means stdin/stdout rather than a real file.
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Open `path` for reading - or stdin, if `path` is [`STDIO_PATH`] - peeking
+/// at the stream's first bytes (without consuming them) to select a decoder:
+///
+/// - `1F 8B` → gzip (decoded with `MultiGzDecoder` so concatenated members,
+///   as produced by `bgzip` or `cat a.fastq.gz b.fastq.gz`, are read to EOF)
+/// - `42 5A 68` ("BZh") → bzip2
+/// - `28 B5 2F FD` → zstd
+/// - `FD 37 7A 58 5A` → xz
+/// - anything else → plain text
+pub fn open_reader(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let inner: Box<dyn Read> = if is_stdio(path) {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(path)?)
+    };
+    sniff_reader(inner)
+}
+
+fn sniff_reader(inner: Box<dyn Read>) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(inner);
+    let magic = reader.fill_buf()?.to_vec();
+
+    if magic.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if magic.starts_with(&BZIP2_MAGIC) {
+        Ok(Box::new(BufReader::new(BzDecoder::new(reader))))
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        Ok(Box::new(BufReader::new(ZstdDecoder::new(reader)?)))
+    } else if magic.starts_with(&XZ_MAGIC) {
+        Ok(Box::new(BufReader::new(XzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Output codec, for callers that need to pick one without a file extension
+/// to infer it from (e.g. writing to stdout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Gzip,
+    Bzip2,
+    Zstd,
+    Xz,
+}
+
+impl Codec {
+    /// Infer a codec from a file extension (`.gz`, `.bz2`, `.zst`, `.xz`),
+    /// defaulting to `None` for anything else.
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Codec::Gzip,
+            Some("bz2") => Codec::Bzip2,
+            Some("zst") => Codec::Zstd,
+            Some("xz") => Codec::Xz,
+            _ => Codec::None,
+        }
+    }
+
+    /// Whether this codec produces non-text output (unsafe to print to a
+    /// terminal).
+    pub fn is_binary(self) -> bool {
+        !matches!(self, Codec::None)
+    }
+}
+
+/// Wrap `inner` with an encoder for `codec` at the given `level` (on whatever
+/// scale the codec uses: 0-9 for gzip/bzip2/xz, 0-22 for zstd; out-of-range
+/// values are clamped by the underlying codec).
+pub fn wrap_writer(inner: Box<dyn Write>, codec: Codec, level: u32) -> io::Result<Box<dyn Write>> {
+    match codec {
+        Codec::None => Ok(inner),
+        Codec::Gzip => Ok(Box::new(GzEncoder::new(inner, GzCompression::new(level)))),
+        Codec::Bzip2 => Ok(Box::new(BzEncoder::new(inner, BzCompression::new(level)))),
+        Codec::Zstd => Ok(Box::new(ZstdEncoder::new(inner, level as i32)?.auto_finish())),
+        Codec::Xz => Ok(Box::new(XzEncoder::new(inner, level))),
+    }
+}
+
+/// Open `path` for writing, selecting an encoder from its extension
+/// (`.gz`, `.bz2`, `.zst`, `.xz`; anything else is written uncompressed).
+/// `level` is the compression level/preset on whatever scale the selected
+/// codec uses.
+pub fn open_writer(path: &Path, level: u32) -> io::Result<Box<dyn Write>> {
+    let file = File::create(path)?;
+    wrap_writer(Box::new(file), Codec::from_extension(path), level)
+}
@@ -1,6 +1,13 @@
 use ahash::{AHashMap, AHashSet};
+use lru::LruCache;
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+pub mod io_compress;
 
 // ============================================================================
 // Configuration Parameters
@@ -15,6 +22,20 @@ use smallvec::SmallVec;
 pub struct DedupParams {
     pub max_offset: usize,
     pub max_error_frac: f64,
+
+    /// Whether both mates must independently confirm similarity against a
+    /// candidate exemplar for the pair to merge (the original behavior). If
+    /// `false`, a match on either mate alone is enough, with the other
+    /// mate's alignment offset assumed equal to the matched one's.
+    pub require_both_mates: bool,
+
+    /// Optional secondary exact-match gate: when a candidate passes the
+    /// offset/error-rate check in [`reads_are_similar`], also require its
+    /// mates be within this many edits (banded Needleman-Wunsch) of the new
+    /// read's mates before merging. Guards against shared-minimizer
+    /// near-collisions over-collapsing genuinely distinct reads. `None`
+    /// (the default) skips this stage entirely.
+    pub max_edits: Option<usize>,
 }
 
 impl Default for DedupParams {
@@ -22,6 +43,8 @@ impl Default for DedupParams {
         Self {
             max_offset: 1,
             max_error_frac: 0.01,
+            require_both_mates: true,
+            max_edits: None,
         }
     }
 }
@@ -86,23 +109,20 @@ pub struct ReadPair {
 
 impl ReadPair {
     pub fn mean_quality(&self) -> f64 {
-        let total: u32 = self.fwd_qual.bytes().chain(self.rev_qual.bytes())
-            .map(|b| (b - 33) as u32)
-            .sum();
-        let count = (self.fwd_qual.len() + self.rev_qual.len()) as f64;
-        if count == 0.0 {
-            return 0.0;
-        }
-        total as f64 / count
+        mean_quality(&self.fwd_qual, &self.rev_qual)
     }
 }
 
 /// Lightweight representation of an exemplar for similarity checking.
-/// Only stores sequences (not quality strings) to reduce memory footprint.
-#[derive(Clone)]
+/// Quality strings are retained (rather than discarded, as sequences alone
+/// would suffice for matching) so [`DedupContext::finalize_with_consensus`]
+/// can fold them into a per-cluster consensus read.
+#[derive(Clone, Serialize, Deserialize)]
 struct StoredExemplar {
     fwd_seq: String,
     rev_seq: String,
+    fwd_qual: String,
+    rev_qual: String,
 }
 
 /// ID registry for interning read IDs to compact u32 indices.
@@ -239,57 +259,332 @@ fn extract_minimizers(seq: &str, params: &MinimizerParams) -> SmallVec<[u64; 8]>
     minimizers
 }
 
+/// Compute the combined minimizer sketch for a read pair (forward then reverse
+/// mate). This is the CPU-heavy, embarrassingly-parallel part of processing a
+/// read: it touches no context state, so callers may compute it for many
+/// pairs concurrently (e.g. across a `rayon` pool) before feeding results into
+/// `DedupContext`'s inherently sequential cluster bookkeeping.
+pub fn compute_minimizers(fwd_seq: &str, rev_seq: &str, params: &MinimizerParams) -> Vec<u64> {
+    let mut all_mins: Vec<u64> = extract_minimizers(fwd_seq, params).into_vec();
+    all_mins.extend(extract_minimizers(rev_seq, params));
+    all_mins
+}
+
+/// Mean Phred quality (in the same convention as `ReadPair::mean_quality`)
+/// across a forward and reverse quality string, for callers that have not
+/// constructed a `ReadPair`.
+fn mean_quality(fwd_qual: &str, rev_qual: &str) -> f64 {
+    let total: u32 = fwd_qual.bytes().chain(rev_qual.bytes())
+        .map(|b| (b - 33) as u32)
+        .sum();
+    let count = (fwd_qual.len() + rev_qual.len()) as f64;
+    if count == 0.0 {
+        return 0.0;
+    }
+    total as f64 / count
+}
+
 // ============================================================================
-// Similarity Checking
+// Quality-Weighted Consensus
 //
-// Allow sequences to match with small alignment shifts (indels) and mismatches.
-// The offset counts as error: e.g., 1bp offset + 1 mismatch = 2 errors total.
+// Rather than picking one "best" read per cluster, accumulate a per-position,
+// per-base log-likelihood across every member as it streams through (so the
+// full read set never needs to be held in memory): for a member base `b`
+// with Phred quality `Q`, let p_err = 10^(-Q/10); this contributes
+// log(1 - p_err) to `b`'s accumulator and log(p_err/3) to the other three
+// bases. The consensus base at a position is the argmax of its accumulators,
+// and its output quality is derived from that base's normalized posterior.
 // ============================================================================
 
-fn check_similarity(
-    seq1: &str,
-    seq2: &str,
-    max_offset: usize,
-    max_error_frac: f64,
-) -> bool {
-    let s1 = seq1.as_bytes();
-    let s2 = seq2.as_bytes();
+const BASE_CHARS: [u8; 4] = [b'A', b'C', b'G', b'T'];
 
-    // Optimized helper function with early exit for hot path performance
-    #[inline]
-    fn check_one_way(seqa: &[u8], seqb: &[u8], off: usize, max_error_frac: f64) -> bool {
-        if off >= seqa.len() {
-            return false;
-        }
-        let overlap_len = (seqa.len() - off).min(seqb.len());
-        if overlap_len == 0 {
-            return false;
+/// Phred quality is clamped into this range before converting to a
+/// probability, so a `Q=0` base doesn't get treated as "certainly wrong"
+/// (p_err would be 1.0) and a very high reported quality doesn't make
+/// `log(p_err/3)` blow up.
+const MIN_P_ERR: f64 = 1e-6;
+const MAX_P_ERR: f64 = 0.75;
+
+/// Cap on the derived consensus Phred quality (encodable as a single
+/// printable FASTQ quality character).
+const MAX_CONSENSUS_PHRED: f64 = 60.0;
+
+/// Per-position base log-likelihood accumulators for both strands (forward
+/// and reverse) of a cluster's consensus, each indexed in the cluster
+/// leader's own read-coordinate frame.
+#[derive(Clone)]
+struct ConsensusAccumulator {
+    fwd: Vec<[f64; 4]>,
+    rev: Vec<[f64; 4]>,
+}
+
+impl ConsensusAccumulator {
+    fn new(fwd_len: usize, rev_len: usize) -> Self {
+        Self {
+            fwd: vec![[0.0; 4]; fwd_len],
+            rev: vec![[0.0; 4]; rev_len],
         }
+    }
+
+    /// Fold one member's forward-mate bases into the forward accumulator,
+    /// shifted by `delta` positions into the leader's coordinate frame.
+    /// Positions that land outside the accumulator (ragged ends) are
+    /// skipped rather than extending it, so cluster coordinates stay fixed
+    /// to the leader's read length.
+    fn accumulate_fwd(&mut self, seq: &str, qual: &str, delta: isize) {
+        Self::accumulate(&mut self.fwd, seq, qual, delta);
+    }
+
+    fn accumulate_rev(&mut self, seq: &str, qual: &str, delta: isize) {
+        Self::accumulate(&mut self.rev, seq, qual, delta);
+    }
 
-        // Pre-calculate error budget to avoid floating-point division in the loop
-        let max_errors = (max_error_frac * overlap_len as f64).floor() as usize;
-        if off > max_errors {
-            return false; // Offset alone exceeds budget
+    fn accumulate(strand: &mut [[f64; 4]], seq: &str, qual: &str, delta: isize) {
+        for (i, (&base, &q)) in seq.as_bytes().iter().zip(qual.as_bytes()).enumerate() {
+            let ref_pos = i as isize + delta;
+            if ref_pos < 0 || ref_pos as usize >= strand.len() {
+                continue;
+            }
+            let Some(code) = encode_base(base) else {
+                continue; // Non-ACGT base: doesn't vote for any base
+            };
+
+            let q_val = q.saturating_sub(33) as f64;
+            let p_err = (10f64.powf(-q_val / 10.0)).clamp(MIN_P_ERR, MAX_P_ERR);
+            let log_match = (1.0 - p_err).ln();
+            let log_mismatch = (p_err / 3.0).ln();
+
+            let slot = &mut strand[ref_pos as usize];
+            for (base_code, ll) in slot.iter_mut().enumerate() {
+                *ll += if base_code as u64 == code { log_match } else { log_mismatch };
+            }
         }
+    }
+}
 
-        let allowed_mismatches = max_errors - off;
-        let mut mismatches = 0;
+/// Derive a consensus sequence and quality string from accumulated
+/// per-position log-likelihoods: the base is the argmax, and its quality is
+/// `-10*log10(1 - posterior)` where `posterior` is the winning base's
+/// normalized (softmax) share of the position's likelihood.
+fn consensus_strand(positions: &[[f64; 4]]) -> (String, String) {
+    let mut seq = String::with_capacity(positions.len());
+    let mut qual = String::with_capacity(positions.len());
+
+    for loglik in positions {
+        let max_ll = loglik.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exp_ll: [f64; 4] = std::array::from_fn(|i| (loglik[i] - max_ll).exp());
+        let sum_exp: f64 = exp_ll.iter().sum();
+
+        let best_code = (0..4)
+            .max_by(|&a, &b| loglik[a].partial_cmp(&loglik[b]).unwrap())
+            .unwrap();
+        let posterior = exp_ll[best_code] / sum_exp;
+        let phred = (-10.0 * (1.0 - posterior).max(10f64.powf(-MAX_CONSENSUS_PHRED / 10.0)).log10())
+            .min(MAX_CONSENSUS_PHRED);
+
+        seq.push(BASE_CHARS[best_code] as char);
+        qual.push((33 + phred.round() as u8) as char);
+    }
+
+    (seq, qual)
+}
+
+// ============================================================================
+// Overlap-Aware Mate Merging
+//
+// For short inserts the forward and reverse mates overlap; deduping on the
+// merged fragment collapses duplicates that differ only in how far mate
+// trimming ate into that overlap. `merge_pair` is a standalone
+// sequence-level helper (it knows nothing about `DedupContext`); feeding the
+// result into the existing clustering machinery is a separate step (see
+// `DedupContext::process_merged_read`).
+// ============================================================================
+
+/// Default maximum Hamming distance tolerated across a merge candidate's
+/// overlapping region, matching typical overlap-merging practice (e.g.
+/// NGmerge/fastp defaults).
+pub const DEFAULT_MAX_OVERLAP_HAMMING: usize = 10;
+
+/// Reverse-complement a DNA sequence. Bases other than A/C/G/T (any case)
+/// pass through unchanged, so rare IUPAC ambiguity codes (and `N`) survive
+/// rather than being rejected.
+fn reverse_complement(seq: &str) -> String {
+    seq.bytes()
+        .rev()
+        .map(|b| match b {
+            b'A' => b'T',
+            b'a' => b't',
+            b'T' => b'A',
+            b't' => b'a',
+            b'C' => b'G',
+            b'c' => b'g',
+            b'G' => b'C',
+            b'g' => b'c',
+            other => other,
+        } as char)
+        .collect()
+}
+
+/// Hamming distance between the last `overlap_len` bases of `fwd` and the
+/// first `overlap_len` bases of `rc_rev` (the reverse mate, already
+/// reverse-complemented). Callers must ensure `overlap_len <= fwd.len()` and
+/// `overlap_len <= rc_rev.len()`.
+fn overlap_hamming(fwd: &[u8], rc_rev: &[u8], overlap_len: usize) -> usize {
+    let fwd_start = fwd.len() - overlap_len;
+    fwd[fwd_start..]
+        .iter()
+        .zip(&rc_rev[..overlap_len])
+        .filter(|(a, b)| a != b)
+        .count()
+}
+
+/// A read pair's forward and reverse mates merged into one overlapping
+/// fragment, as produced by [`merge_pair`].
+pub struct MergedFragment {
+    pub seq: String,
+    pub qual: String,
+    pub overlap_len: usize,
+}
 
-        let a_part = &seqa[off..off + overlap_len];
-        let b_part = &seqb[..overlap_len];
+/// Minimum overlap length considered when auto-detecting the merge overlap
+/// (no `insert_size` hint). Without a floor, scanning all the way down to a
+/// 1bp overlap lets a spurious single-base coincidental match - normalized
+/// Hamming distance 0.0, the global minimum - win over the true, much longer
+/// fragment overlap, merging almost every pair at a spurious 1bp join. 10bp
+/// mirrors typical overlap-merging tool floors (e.g. NGmerge/fastp).
+const MIN_MERGE_OVERLAP: usize = 10;
 
-        // Manual loop for early exit when error budget is exceeded
-        for i in 0..overlap_len {
-            if a_part[i] != b_part[i] {
-                mismatches += 1;
-                if mismatches > allowed_mismatches {
-                    return false; // Short-circuit early!
+/// Merge `rp`'s forward and reverse mates into a single fragment over their
+/// overlap, or return `None` if no acceptable overlap exists.
+///
+/// The reverse mate is reverse-complemented, and the overlap length is
+/// taken as `fwd_len + rev_len - insert_size` (clamped to
+/// `0..=min(fwd_len, rev_len)`) when `insert_size` is known; otherwise
+/// candidate overlap lengths from `max_overlap` down to
+/// `MIN_MERGE_OVERLAP` are scanned longest-first (if the shorter read is
+/// itself shorter than `MIN_MERGE_OVERLAP`, only `max_overlap` is tried), and the one minimizing Hamming distance
+/// normalized by overlap length is used - ties keep the longest candidate
+/// found so far, since the scan visits long candidates before short ones.
+/// The merge is accepted only if the chosen overlap's (unnormalized)
+/// Hamming distance is at most `max_hamming`. Within the overlap the
+/// higher-quality base wins at each position (ties keep the forward mate's
+/// base); the non-overlapping flanks are concatenated around it unchanged.
+pub fn merge_pair(rp: &ReadPair, insert_size: Option<usize>, max_hamming: usize) -> Option<MergedFragment> {
+    let fwd_seq = rp.fwd_seq.as_bytes();
+    let fwd_qual = rp.fwd_qual.as_bytes();
+    let rc_rev_seq_owned = reverse_complement(&rp.rev_seq);
+    let rc_rev_qual_owned: String = rp.rev_qual.chars().rev().collect();
+    let rc_rev_seq = rc_rev_seq_owned.as_bytes();
+    let rc_rev_qual = rc_rev_qual_owned.as_bytes();
+
+    let fwd_len = fwd_seq.len();
+    let rev_len = rc_rev_seq.len();
+    let max_overlap = fwd_len.min(rev_len);
+
+    let overlap_len = match insert_size {
+        Some(insert_size) => (fwd_len + rev_len).saturating_sub(insert_size).min(max_overlap),
+        None => {
+            let floor = MIN_MERGE_OVERLAP.min(max_overlap).max(1);
+            let mut best: Option<(usize, f64)> = None;
+            // Longest-to-shortest: a tie in normalized distance keeps the
+            // longer candidate already found, rather than being replaced by
+            // a shorter, spuriously-equal one (the strict `<` below only
+            // updates `best` on a genuine improvement).
+            for candidate in (floor..=max_overlap).rev() {
+                let normalized = overlap_hamming(fwd_seq, rc_rev_seq, candidate) as f64 / candidate as f64;
+                let better = match best {
+                    Some((_, best_norm)) => normalized < best_norm,
+                    None => true,
+                };
+                if better {
+                    best = Some((candidate, normalized));
                 }
             }
+            best.map_or(0, |(candidate, _)| candidate)
         }
-        true
+    };
+
+    if overlap_len == 0 {
+        return None;
+    }
+    if overlap_hamming(fwd_seq, rc_rev_seq, overlap_len) > max_hamming {
+        return None;
+    }
+
+    let fwd_flank_len = fwd_len - overlap_len;
+    let mut seq = String::with_capacity(fwd_len + rev_len - overlap_len);
+    let mut qual = String::with_capacity(fwd_len + rev_len - overlap_len);
+
+    seq.push_str(std::str::from_utf8(&fwd_seq[..fwd_flank_len]).unwrap());
+    qual.push_str(std::str::from_utf8(&fwd_qual[..fwd_flank_len]).unwrap());
+
+    for i in 0..overlap_len {
+        let (fwd_base, fwd_q) = (fwd_seq[fwd_flank_len + i], fwd_qual[fwd_flank_len + i]);
+        let (rev_base, rev_q) = (rc_rev_seq[i], rc_rev_qual[i]);
+        let (base, q) = if rev_q > fwd_q { (rev_base, rev_q) } else { (fwd_base, fwd_q) };
+        seq.push(base as char);
+        qual.push(q as char);
     }
 
+    seq.push_str(std::str::from_utf8(&rc_rev_seq[overlap_len..]).unwrap());
+    qual.push_str(std::str::from_utf8(&rc_rev_qual[overlap_len..]).unwrap());
+
+    Some(MergedFragment { seq, qual, overlap_len })
+}
+
+// ============================================================================
+// Similarity Checking
+//
+// Allow sequences to match with small alignment shifts (indels) and mismatches.
+// The offset counts as error: e.g., 1bp offset + 1 mismatch = 2 errors total.
+// ============================================================================
+
+// Optimized helper function with early exit for hot path performance. Tests
+// whether `seqb` (unshifted) matches `seqa` starting at `seqa[off]`, i.e.
+// `seqa` has `off` extra leading bases relative to `seqb`.
+#[inline]
+fn check_one_way(seqa: &[u8], seqb: &[u8], off: usize, max_error_frac: f64) -> bool {
+    if off >= seqa.len() {
+        return false;
+    }
+    let overlap_len = (seqa.len() - off).min(seqb.len());
+    if overlap_len == 0 {
+        return false;
+    }
+
+    // Pre-calculate error budget to avoid floating-point division in the loop
+    let max_errors = (max_error_frac * overlap_len as f64).floor() as usize;
+    if off > max_errors {
+        return false; // Offset alone exceeds budget
+    }
+
+    let allowed_mismatches = max_errors - off;
+    let mut mismatches = 0;
+
+    let a_part = &seqa[off..off + overlap_len];
+    let b_part = &seqb[..overlap_len];
+
+    // Manual loop for early exit when error budget is exceeded
+    for i in 0..overlap_len {
+        if a_part[i] != b_part[i] {
+            mismatches += 1;
+            if mismatches > allowed_mismatches {
+                return false; // Short-circuit early!
+            }
+        }
+    }
+    true
+}
+
+fn check_similarity(
+    seq1: &str,
+    seq2: &str,
+    max_offset: usize,
+    max_error_frac: f64,
+) -> bool {
+    let s1 = seq1.as_bytes();
+    let s2 = seq2.as_bytes();
+
     for offset in 0..=max_offset {
         // Check with s1 shifted left relative to s2
         if check_one_way(s1, s2, offset, max_error_frac) {
@@ -305,7 +600,34 @@ fn check_similarity(
     false
 }
 
-/// Check if two read pairs are similar enough to be duplicates.
+/// Like [`check_similarity`], but on a match also reports the shift needed
+/// to align `query` into `reference`'s coordinate frame: the offset `delta`
+/// such that `reference[i + delta] == query[i]` over the matched overlap
+/// (`delta` may be negative). Used by the consensus accumulator to fold a
+/// new cluster member's bases into the leader's reference coordinates.
+fn find_offset(reference: &[u8], query: &[u8], max_offset: usize, max_error_frac: f64) -> Option<isize> {
+    for offset in 0..=max_offset {
+        if check_one_way(reference, query, offset, max_error_frac) {
+            return Some(offset as isize);
+        }
+        if offset > 0 && check_one_way(query, reference, offset, max_error_frac) {
+            return Some(-(offset as isize));
+        }
+    }
+    None
+}
+
+/// Shift needed to fold a newly-matched read's forward/reverse mates into
+/// the cluster leader's reference coordinate frame (see [`find_offset`]),
+/// along with whether the match was in swapped-mate orientation.
+struct MatchOffsets {
+    swapped: bool,
+    fwd_delta: isize,
+    rev_delta: isize,
+}
+
+/// Check if two read pairs are similar enough to be duplicates, returning
+/// the alignment offsets of the match (for consensus accumulation) if so.
 ///
 /// Checks two orientations (matching Python's ORIENT_TOLERANT mode):
 /// 1. Standard: (Fwd, Rev) vs (Fwd, Rev)
@@ -315,23 +637,281 @@ fn check_similarity(
 /// orientation, causing the same DNA fragment to be sequenced with forward/reverse
 /// swapped. Note: Rust version always uses tolerant mode (no strict mode option).
 fn reads_are_similar(
-    rp: &ReadPair,
+    fwd_seq: &str,
+    rev_seq: &str,
     exemplar: &StoredExemplar,
     dedup_params: &DedupParams,
-) -> bool {
-    if check_similarity(&rp.fwd_seq, &exemplar.fwd_seq, dedup_params.max_offset, dedup_params.max_error_frac)
-        && check_similarity(&rp.rev_seq, &exemplar.rev_seq, dedup_params.max_offset, dedup_params.max_error_frac)
-    {
-        return true;
+) -> Option<MatchOffsets> {
+    let max_offset = dedup_params.max_offset;
+    let max_error_frac = dedup_params.max_error_frac;
+
+    let standard = mate_pair_offsets(
+        find_offset(exemplar.fwd_seq.as_bytes(), fwd_seq.as_bytes(), max_offset, max_error_frac),
+        find_offset(exemplar.rev_seq.as_bytes(), rev_seq.as_bytes(), max_offset, max_error_frac),
+        false,
+        dedup_params.require_both_mates,
+    );
+    if standard.is_some() {
+        return standard;
     }
 
-    if check_similarity(&rp.fwd_seq, &exemplar.rev_seq, dedup_params.max_offset, dedup_params.max_error_frac)
-        && check_similarity(&rp.rev_seq, &exemplar.fwd_seq, dedup_params.max_offset, dedup_params.max_error_frac)
-    {
-        return true;
+    mate_pair_offsets(
+        find_offset(exemplar.rev_seq.as_bytes(), fwd_seq.as_bytes(), max_offset, max_error_frac),
+        find_offset(exemplar.fwd_seq.as_bytes(), rev_seq.as_bytes(), max_offset, max_error_frac),
+        true,
+        dedup_params.require_both_mates,
+    )
+}
+
+/// Combine a pair of per-mate [`find_offset`] results into [`MatchOffsets`]
+/// for one orientation (standard or swapped), honoring
+/// `DedupParams::require_both_mates`: when `false`, a single matched mate is
+/// enough, and the unmatched mate's delta is assumed equal to the matched
+/// one's (the common case for overlapping fragments, where both mates shift
+/// together).
+fn mate_pair_offsets(
+    fwd_delta: Option<isize>,
+    rev_delta: Option<isize>,
+    swapped: bool,
+    require_both_mates: bool,
+) -> Option<MatchOffsets> {
+    match (fwd_delta, rev_delta) {
+        (Some(fwd_delta), Some(rev_delta)) => Some(MatchOffsets { swapped, fwd_delta, rev_delta }),
+        (Some(delta), None) | (None, Some(delta)) if !require_both_mates => {
+            Some(MatchOffsets { swapped, fwd_delta: delta, rev_delta: delta })
+        }
+        _ => None,
     }
+}
 
-    false
+/// Edit distance between `a` and `b` via banded Needleman-Wunsch, or `None`
+/// if it exceeds `max_edits`. The band (width `2 * max_edits + 1`) is
+/// centered on the main diagonal; cells outside it are treated as +infinity.
+/// Bails out as soon as an entire row's minimum score exceeds `max_edits`,
+/// since every cell in every later row can only be reached through it.
+fn banded_edit_distance(a: &[u8], b: &[u8], max_edits: usize) -> Option<usize> {
+    let (n, m) = (a.len(), b.len());
+    if n.abs_diff(m) > max_edits {
+        return None;
+    }
+
+    const INF: usize = usize::MAX / 4;
+    let mut prev = vec![INF; m + 1];
+    for (j, cell) in prev.iter_mut().enumerate().take((max_edits + 1).min(m + 1)) {
+        *cell = j;
+    }
+
+    for i in 1..=n {
+        let lo = i.saturating_sub(max_edits);
+        let hi = (i + max_edits).min(m);
+
+        let mut curr = vec![INF; m + 1];
+        let mut row_min = INF;
+        if lo == 0 {
+            curr[0] = i;
+            row_min = i;
+        }
+
+        for j in lo.max(1)..=hi {
+            let sub_cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let diag = prev[j - 1];
+            let up = prev[j];
+            let left = curr[j - 1];
+            let best = (diag + sub_cost).min(up + 1).min(left + 1);
+            curr[j] = best;
+            row_min = row_min.min(best);
+        }
+
+        if row_min > max_edits {
+            return None;
+        }
+        prev = curr;
+    }
+
+    let dist = prev[m];
+    (dist <= max_edits).then_some(dist)
+}
+
+// ============================================================================
+// Storage Backend
+//
+// Buckets and exemplars are the two structures that grow with the number of
+// unique sequences seen, so they're the two that get an on-disk option for
+// corpora whose working set no longer fits in memory. Both stores keep the
+// streaming algorithm's access pattern (point lookup, point insert, full
+// clear at `finalize`) identical regardless of backend; only where the bytes
+// live changes.
+// ============================================================================
+
+/// Where a [`DedupContext`] keeps its minimizer buckets and exemplar
+/// records.
+///
+/// `InMemory` (the default) matches every caller from before this option
+/// existed. `OnDisk` is for corpora whose bucket/exemplar working set no
+/// longer fits in memory: both are persisted to an embedded on-disk store
+/// rooted at `path`, with an in-memory LRU cache of approximately
+/// `cache_bytes` worth of the hottest entries so the common case (a
+/// minimizer bucket or exemplar touched recently) stays fast.
+pub enum StorageBackend {
+    InMemory,
+    OnDisk { path: PathBuf, cache_bytes: usize },
+}
+
+/// Rough per-entry overhead (key + sled/cache bookkeeping) used to convert
+/// `cache_bytes` into an LRU entry count. Deliberately coarse: exact sizing
+/// isn't worth tracking per-value for a cache that's just smoothing out
+/// re-fetches of recently touched keys.
+const APPROX_CACHE_ENTRY_BYTES: usize = 256;
+
+fn cache_capacity(cache_bytes: usize) -> NonZeroUsize {
+    NonZeroUsize::new((cache_bytes / APPROX_CACHE_ENTRY_BYTES).max(1)).unwrap()
+}
+
+/// Minimizer -> candidate read index buckets, backed by either an in-memory
+/// map or an on-disk store with an LRU cache in front of it.
+enum BucketStore {
+    InMemory(FxHashMap<u64, Vec<u32>>),
+    OnDisk {
+        db: sled::Db,
+        cache: LruCache<u64, Vec<u32>>,
+    },
+}
+
+impl BucketStore {
+    fn new(backend: &StorageBackend) -> Self {
+        match backend {
+            StorageBackend::InMemory => BucketStore::InMemory(FxHashMap::default()),
+            StorageBackend::OnDisk { path, cache_bytes } => {
+                let db = sled::open(path.join("buckets")).expect("failed to open on-disk bucket store");
+                BucketStore::OnDisk { db, cache: LruCache::new(cache_capacity(*cache_bytes)) }
+            }
+        }
+    }
+
+    /// Look up the candidate list for `key`. The in-memory backend returns a
+    /// borrow straight out of its map - this sits in the per-minimizer hot
+    /// loop, so it mustn't clone on the default (in-memory) path. The on-disk
+    /// backend has no way around an owned return (its cache/deserialize step
+    /// already produced one), so it's the only arm paying for `Cow::Owned`.
+    fn get(&mut self, key: u64) -> Option<Cow<'_, [u32]>> {
+        match self {
+            BucketStore::InMemory(map) => map.get(&key).map(|v| Cow::Borrowed(v.as_slice())),
+            BucketStore::OnDisk { db, cache } => {
+                if let Some(hit) = cache.get(&key) {
+                    return Some(Cow::Owned(hit.clone()));
+                }
+                let loaded = db
+                    .get(key.to_be_bytes())
+                    .expect("on-disk bucket store read failed")
+                    .map(|bytes| bincode::deserialize::<Vec<u32>>(&bytes).expect("corrupt bucket record"));
+                if let Some(value) = &loaded {
+                    cache.put(key, value.clone());
+                }
+                loaded.map(Cow::Owned)
+            }
+        }
+    }
+
+    fn push(&mut self, key: u64, read_idx: u32) {
+        match self {
+            BucketStore::InMemory(map) => map.entry(key).or_insert_with(Vec::new).push(read_idx),
+            BucketStore::OnDisk { db, cache } => {
+                let mut bucket = cache.get(&key).cloned().unwrap_or_else(|| {
+                    db.get(key.to_be_bytes())
+                        .expect("on-disk bucket store read failed")
+                        .map(|bytes| bincode::deserialize::<Vec<u32>>(&bytes).expect("corrupt bucket record"))
+                        .unwrap_or_default()
+                });
+                bucket.push(read_idx);
+                let encoded = bincode::serialize(&bucket).expect("failed to encode bucket record");
+                db.insert(key.to_be_bytes(), encoded).expect("on-disk bucket store write failed");
+                cache.put(key, bucket);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            BucketStore::InMemory(map) => map.clear(),
+            BucketStore::OnDisk { db, cache } => {
+                db.clear().expect("failed to clear on-disk bucket store");
+                cache.clear();
+            }
+        }
+    }
+}
+
+/// Read index -> exemplar record, backed by either an in-memory vector or an
+/// on-disk store with an LRU cache in front of it.
+enum ExemplarStore {
+    InMemory(Vec<Option<StoredExemplar>>),
+    OnDisk {
+        db: sled::Db,
+        cache: LruCache<u32, StoredExemplar>,
+    },
+}
+
+impl ExemplarStore {
+    fn new(backend: &StorageBackend) -> Self {
+        match backend {
+            StorageBackend::InMemory => ExemplarStore::InMemory(Vec::new()),
+            StorageBackend::OnDisk { path, cache_bytes } => {
+                let db = sled::open(path.join("exemplars")).expect("failed to open on-disk exemplar store");
+                ExemplarStore::OnDisk { db, cache: LruCache::new(cache_capacity(*cache_bytes)) }
+            }
+        }
+    }
+
+    /// Look up the exemplar for `read_idx`. As with [`BucketStore::get`], the
+    /// in-memory backend returns a borrow rather than cloning, since this is
+    /// called once per candidate in the dedup hot loop; only the on-disk
+    /// backend's cache/deserialize step forces an owned value.
+    fn get(&mut self, read_idx: u32) -> Option<Cow<'_, StoredExemplar>> {
+        match self {
+            ExemplarStore::InMemory(store) => {
+                store.get(read_idx as usize).and_then(|opt| opt.as_ref()).map(Cow::Borrowed)
+            }
+            ExemplarStore::OnDisk { db, cache } => {
+                if let Some(hit) = cache.get(&read_idx) {
+                    return Some(Cow::Owned(hit.clone()));
+                }
+                let loaded = db
+                    .get(read_idx.to_be_bytes())
+                    .expect("on-disk exemplar store read failed")
+                    .map(|bytes| bincode::deserialize::<StoredExemplar>(&bytes).expect("corrupt exemplar record"));
+                if let Some(value) = &loaded {
+                    cache.put(read_idx, value.clone());
+                }
+                loaded.map(Cow::Owned)
+            }
+        }
+    }
+
+    fn insert(&mut self, read_idx: u32, exemplar: StoredExemplar) {
+        match self {
+            ExemplarStore::InMemory(store) => {
+                if store.len() <= read_idx as usize {
+                    store.resize(read_idx as usize + 1, None);
+                }
+                store[read_idx as usize] = Some(exemplar);
+            }
+            ExemplarStore::OnDisk { db, cache } => {
+                let encoded = bincode::serialize(&exemplar).expect("failed to encode exemplar record");
+                db.insert(read_idx.to_be_bytes(), encoded).expect("on-disk exemplar store write failed");
+                cache.put(read_idx, exemplar);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            ExemplarStore::InMemory(store) => store.clear(),
+            ExemplarStore::OnDisk { db, cache } => {
+                db.clear().expect("failed to clear on-disk exemplar store");
+                cache.clear();
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -347,11 +927,16 @@ fn reads_are_similar(
 // lookups to work correctly.
 // ============================================================================
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 struct ClusterStats {
     best_read_idx: u32,  // Index of best read (can change as we see better reads)
     best_score: f64,
     count: usize,
+
+    // Present only when the owning `DedupContext` has consensus enabled
+    // (see `DedupContext::enable_consensus`); accumulates every member's
+    // bases as they stream through.
+    consensus: Option<ConsensusAccumulator>,
 }
 
 pub struct DedupContext {
@@ -366,10 +951,10 @@ pub struct DedupContext {
     //   integers, so we can use the ultra-fast FxHash (just a multiply + XOR)
 
     // minimizer -> list of read indices (instead of read IDs)
-    buckets: FxHashMap<u64, Vec<u32>>,
+    buckets: BucketStore,
 
-    // read_idx -> read sequences (only for exemplars, quality strings omitted)
-    exemplar_store: Vec<Option<StoredExemplar>>,
+    // read_idx -> read sequences and qualities (only for exemplars)
+    exemplar_store: ExemplarStore,
 
     // read_idx -> cluster_leader_idx (grows linearly with total reads)
     results: Vec<u32>,
@@ -378,22 +963,48 @@ pub struct DedupContext {
     clusters: FxHashMap<u32, ClusterStats>,
 
     finalized: bool,
+
+    // When set, each cluster's ClusterStats accumulates a consensus over
+    // all its members (see `enable_consensus`/`finalize_with_consensus`)
+    // instead of only tracking the best-scoring read.
+    consensus_enabled: bool,
 }
 
 impl DedupContext {
+    /// Create a context with the default in-memory storage backend. Use
+    /// [`DedupContext::with_storage_backend`] for corpora whose bucket/
+    /// exemplar working set is too large to hold in memory.
     pub fn new(dedup_params: DedupParams, minimizer_params: MinimizerParams) -> Self {
+        Self::with_storage_backend(dedup_params, minimizer_params, StorageBackend::InMemory)
+    }
+
+    /// Create a context backed by `storage`. See [`StorageBackend`].
+    pub fn with_storage_backend(
+        dedup_params: DedupParams,
+        minimizer_params: MinimizerParams,
+        storage: StorageBackend,
+    ) -> Self {
         Self {
             dedup_params,
             minimizer_params,
             id_registry: IDRegistry::new(),
-            buckets: FxHashMap::default(),
-            exemplar_store: Vec::new(),
+            buckets: BucketStore::new(&storage),
+            exemplar_store: ExemplarStore::new(&storage),
             results: Vec::new(),
             clusters: FxHashMap::default(),
             finalized: false,
+            consensus_enabled: false,
         }
     }
 
+    /// Enable per-cluster consensus accumulation (see
+    /// [`DedupContext::finalize_with_consensus`]). Must be called before any
+    /// reads are processed, since it changes what gets recorded as each
+    /// cluster's first member arrives.
+    pub fn enable_consensus(&mut self) {
+        self.consensus_enabled = true;
+    }
+
     /// Process one read pair. Returns the cluster ID it was assigned to.
     ///
     /// Algorithm:
@@ -405,40 +1016,139 @@ impl DedupContext {
         // Intern the read ID to a compact u32 index
         let read_idx = self.id_registry.get_or_create(&read_pair.read_id);
         let mean_q = read_pair.mean_quality();
+        let minimizers = compute_minimizers(&read_pair.fwd_seq, &read_pair.rev_seq, &self.minimizer_params);
+
+        let cluster_leader_idx = self.process_read_indexed(
+            read_idx,
+            read_pair.fwd_seq,
+            read_pair.rev_seq,
+            read_pair.fwd_qual,
+            read_pair.rev_qual,
+            mean_q,
+            &minimizers,
+        );
 
-        // Calculate score: quality is primary (scaled by 1000), length is secondary
-        let length = (read_pair.fwd_seq.len() + read_pair.rev_seq.len()) as f64;
-        let score = mean_q * 1000.0 + length;
+        // Return the cluster leader's ID (as a String)
+        self.id_registry.get_id(cluster_leader_idx).to_string()
+    }
+
+    /// Process one read pair identified by a plain numeric index rather than a
+    /// string ID. This skips `id_registry` interning entirely (more efficient
+    /// than creating a `ReadPair` with a synthetic string ID), which suits
+    /// callers such as `dedup_interleaved_fastq` that already have a natural
+    /// integer index for each pair. Returns the cluster leader's index.
+    pub fn process_read_by_index(
+        &mut self,
+        read_idx: usize,
+        fwd_seq: String,
+        rev_seq: String,
+        fwd_qual: String,
+        rev_qual: String,
+    ) -> u32 {
+        let minimizers = compute_minimizers(&fwd_seq, &rev_seq, &self.minimizer_params);
+        let mean_q = mean_quality(&fwd_qual, &rev_qual);
+        self.process_read_indexed(read_idx as u32, fwd_seq, rev_seq, fwd_qual, rev_qual, mean_q, &minimizers)
+    }
+
+    /// Process a pre-merged single fragment (see [`merge_pair`]) through the
+    /// same minimizer/bucket clustering machinery used for paired reads, by
+    /// presenting the merged fragment as both "mates" of the pair. This lets
+    /// overlapping duplicates that were merged/trimmed slightly differently
+    /// still land in the same cluster as a genuinely identical pair would.
+    /// Returns the cluster leader's index.
+    pub fn process_merged_read(&mut self, read_idx: usize, seq: String, qual: String) -> u32 {
+        self.process_read_by_index(read_idx, seq.clone(), seq, qual.clone(), qual)
+    }
+
+    /// Same as [`DedupContext::process_read_by_index`], but accepts a
+    /// minimizer sketch computed ahead of time. This lets a caller parallelize
+    /// the CPU-heavy, embarrassingly-parallel sketching step (e.g. across a
+    /// `rayon` worker pool) while still feeding results into this context's
+    /// inherently sequential cluster bookkeeping in index order.
+    pub fn process_read_by_index_with_minimizers(
+        &mut self,
+        read_idx: usize,
+        fwd_seq: String,
+        rev_seq: String,
+        fwd_qual: String,
+        rev_qual: String,
+        minimizers: &[u64],
+    ) -> u32 {
+        let mean_q = mean_quality(&fwd_qual, &rev_qual);
+        self.process_read_indexed(read_idx as u32, fwd_seq, rev_seq, fwd_qual, rev_qual, mean_q, minimizers)
+    }
 
-        let fwd_mins = extract_minimizers(&read_pair.fwd_seq, &self.minimizer_params);
-        let rev_mins = extract_minimizers(&read_pair.rev_seq, &self.minimizer_params);
+    /// Secondary exact-match gate applied on top of [`reads_are_similar`]'s
+    /// offset/error-rate check (see `DedupParams::max_edits`): rejects
+    /// shared-minimizer near-collisions whose mates aren't also within
+    /// `max_edits` banded edit distance of each other. A no-op (always
+    /// `true`) when `max_edits` is `None`.
+    ///
+    /// A free function (not a method) like [`reads_are_similar`], taking
+    /// `dedup_params` directly: the dedup hot loop holds a borrow of
+    /// `self.exemplar_store` (via [`ExemplarStore::get`]) while calling this,
+    /// and a `&self` method would conflict with that borrow.
+    fn edit_distance_ok(
+        fwd_seq: &str,
+        rev_seq: &str,
+        candidate: &StoredExemplar,
+        offsets: &MatchOffsets,
+        dedup_params: &DedupParams,
+    ) -> bool {
+        let Some(max_edits) = dedup_params.max_edits else {
+            return true;
+        };
+        let (cand_fwd, cand_rev) = if offsets.swapped {
+            (candidate.rev_seq.as_str(), candidate.fwd_seq.as_str())
+        } else {
+            (candidate.fwd_seq.as_str(), candidate.rev_seq.as_str())
+        };
+        banded_edit_distance(cand_fwd.as_bytes(), fwd_seq.as_bytes(), max_edits).is_some()
+            && banded_edit_distance(cand_rev.as_bytes(), rev_seq.as_bytes(), max_edits).is_some()
+    }
 
-        let mut all_mins = fwd_mins;
-        all_mins.extend(rev_mins);
+    /// Core clustering step shared by [`DedupContext::process_read`] and the
+    /// index-based entry points: look up candidates sharing a minimizer,
+    /// confirm similarity, and update (or create) the candidate's cluster.
+    fn process_read_indexed(
+        &mut self,
+        read_idx: u32,
+        fwd_seq: String,
+        rev_seq: String,
+        fwd_qual: String,
+        rev_qual: String,
+        mean_q: f64,
+        minimizers: &[u64],
+    ) -> u32 {
+        // Calculate score: quality is primary (scaled by 1000), length is secondary
+        let length = (fwd_seq.len() + rev_seq.len()) as f64;
+        let score = mean_q * 1000.0 + length;
 
         // Track which candidates we've already checked
         let mut checked_indices = AHashSet::new();
-        let mut matching_cluster_idx: Option<u32> = None;
+        let mut matching: Option<(u32, MatchOffsets)> = None;
 
-        'outer: for &min_hash in &all_mins {
-            if let Some(bucket_reads) = self.buckets.get(&min_hash) {
-                for &candidate_idx in bucket_reads {
+        'outer: for &min_hash in minimizers {
+            if let Some(bucket_reads) = self.buckets.get(min_hash) {
+                for &candidate_idx in bucket_reads.iter() {
                     if !checked_indices.insert(candidate_idx) {
                         continue;  // Already checked this candidate
                     }
 
-                    if let Some(candidate) = self.exemplar_store.get(candidate_idx as usize).and_then(|opt| opt.as_ref()) {
-                        if reads_are_similar(&read_pair, candidate, &self.dedup_params) {
-                            // candidate_idx from buckets is always a cluster leader
-                            matching_cluster_idx = Some(candidate_idx);
-                            break 'outer;
+                    if let Some(candidate) = self.exemplar_store.get(candidate_idx) {
+                        if let Some(offsets) = reads_are_similar(&fwd_seq, &rev_seq, &candidate, &self.dedup_params) {
+                            if Self::edit_distance_ok(&fwd_seq, &rev_seq, &candidate, &offsets, &self.dedup_params) {
+                                // candidate_idx from buckets is always a cluster leader
+                                matching = Some((candidate_idx, offsets));
+                                break 'outer;
+                            }
                         }
                     }
                 }
             }
         }
 
-        let cluster_leader_idx = if let Some(cluster_idx) = matching_cluster_idx {
+        let cluster_leader_idx = if let Some((cluster_idx, offsets)) = matching {
             // Found a match - add to existing cluster
             if let Some(cluster) = self.clusters.get_mut(&cluster_idx) {
                 cluster.count += 1;
@@ -447,33 +1157,51 @@ impl DedupContext {
                     cluster.best_read_idx = read_idx;
                     cluster.best_score = score;
                 }
+                if let Some(consensus) = &mut cluster.consensus {
+                    // In the swapped orientation, this read's forward mate
+                    // aligned against the leader's reverse mate (and vice
+                    // versa), so it folds into the other accumulator.
+                    if offsets.swapped {
+                        consensus.accumulate_rev(&fwd_seq, &fwd_qual, offsets.fwd_delta);
+                        consensus.accumulate_fwd(&rev_seq, &rev_qual, offsets.rev_delta);
+                    } else {
+                        consensus.accumulate_fwd(&fwd_seq, &fwd_qual, offsets.fwd_delta);
+                        consensus.accumulate_rev(&rev_seq, &rev_qual, offsets.rev_delta);
+                    }
+                }
             }
             cluster_idx
         } else {
             // New unique sequence - create new cluster with this read as leader
+            let consensus = if self.consensus_enabled {
+                let mut acc = ConsensusAccumulator::new(fwd_seq.len(), rev_seq.len());
+                acc.accumulate_fwd(&fwd_seq, &fwd_qual, 0);
+                acc.accumulate_rev(&rev_seq, &rev_qual, 0);
+                Some(acc)
+            } else {
+                None
+            };
+
             self.clusters.insert(
                 read_idx,
                 ClusterStats {
                     best_read_idx: read_idx,
                     best_score: score,
                     count: 1,
+                    consensus,
                 },
             );
 
-            // Ensure exemplar_store has space for this index
-            if self.exemplar_store.len() <= read_idx as usize {
-                self.exemplar_store.resize(read_idx as usize + 1, None);
-            }
-
-            // Store only sequences (not quality strings) to reduce memory footprint
-            self.exemplar_store[read_idx as usize] = Some(StoredExemplar {
-                fwd_seq: read_pair.fwd_seq,
-                rev_seq: read_pair.rev_seq,
-            });
+            // Store sequences and quality strings (quality is needed for
+            // consensus accumulation by later cluster members)
+            self.exemplar_store.insert(
+                read_idx,
+                StoredExemplar { fwd_seq, rev_seq, fwd_qual, rev_qual },
+            );
 
             // Add read to minimizer buckets (only for new exemplars)
-            for &min_hash in &all_mins {
-                self.buckets.entry(min_hash).or_insert_with(Vec::new).push(read_idx);
+            for &min_hash in minimizers {
+                self.buckets.push(min_hash, read_idx);
             }
 
             read_idx
@@ -485,8 +1213,15 @@ impl DedupContext {
         }
         self.results[read_idx as usize] = cluster_leader_idx;
 
-        // Return the cluster leader's ID (as a String)
-        self.id_registry.get_id(cluster_leader_idx).to_string()
+        cluster_leader_idx
+    }
+
+    /// Return the set of read indices that are exemplars (cluster leaders)
+    /// after [`DedupContext::finalize`] has run. Intended for index-based
+    /// callers (see [`DedupContext::process_read_by_index`]) that never
+    /// registered string IDs and so can't use [`DedupContext::get_cluster_id`].
+    pub fn get_exemplar_indices(&self) -> AHashSet<u32> {
+        self.results.iter().copied().collect()
     }
 
     /// Finalize results: resolve all reads to their cluster's best_read_idx.
@@ -509,6 +1244,38 @@ impl DedupContext {
         self.exemplar_store.clear();
     }
 
+    /// Like [`DedupContext::finalize`], but also returns a maximum-likelihood
+    /// consensus read pair per cluster, built from every member's bases
+    /// (weighted by their Phred qualities) rather than picking a single best
+    /// read. Requires [`DedupContext::enable_consensus`] to have been called
+    /// before any reads were processed; otherwise the returned map is empty.
+    /// Keyed by cluster leader index, matching the index-based entry points.
+    pub fn finalize_with_consensus(&mut self) -> FxHashMap<u32, ReadPair> {
+        self.finalize();
+
+        let mut consensus_reads = FxHashMap::default();
+        for (&leader_idx, cluster) in &self.clusters {
+            let Some(consensus) = &cluster.consensus else {
+                continue;
+            };
+            let (fwd_seq, fwd_qual) = consensus_strand(&consensus.fwd);
+            let (rev_seq, rev_qual) = consensus_strand(&consensus.rev);
+            let read_id = self
+                .id_registry
+                .index_to_id
+                .get(leader_idx as usize)
+                .cloned()
+                .unwrap_or_else(|| leader_idx.to_string());
+
+            consensus_reads.insert(
+                leader_idx,
+                ReadPair { read_id, fwd_seq, rev_seq, fwd_qual, rev_qual },
+            );
+        }
+
+        consensus_reads
+    }
+
     pub fn get_cluster_id(&self, read_id: &str) -> String {
         if let Some(&read_idx) = self.id_registry.id_to_index.get(read_id) {
             if let Some(&cluster_idx) = self.results.get(read_idx as usize) {
@@ -523,6 +1290,82 @@ impl DedupContext {
         let unique_clusters = self.clusters.len();
         (total_reads, unique_clusters)
     }
+
+    /// Finalize and return a full [`DedupResult`]: one [`ClusterRecord`] per
+    /// cluster (leader, every member, stats, and - if
+    /// [`DedupContext::enable_consensus`] was called - a consensus read)
+    /// rather than just the flattened `read_id -> leader_id` map that
+    /// [`DedupContext::finalize`]/[`deduplicate_read_pairs`] expose.
+    pub fn finalize_rich(&mut self) -> DedupResult {
+        // `self.results` still maps read_idx -> original cluster leader_idx
+        // at this point; `finalize()` (below) overwrites it in place to
+        // point at each cluster's best exemplar instead, so membership has
+        // to be captured before that happens.
+        let mut member_ids_by_leader: FxHashMap<u32, Vec<String>> = FxHashMap::default();
+        for read_idx in 0..self.results.len() {
+            let leader_idx = self.results[read_idx];
+            let read_id = self.id_registry.get_id(read_idx as u32).to_string();
+            member_ids_by_leader.entry(leader_idx).or_insert_with(Vec::new).push(read_id);
+        }
+
+        let consensus_reads = if self.consensus_enabled {
+            self.finalize_with_consensus()
+        } else {
+            self.finalize();
+            FxHashMap::default()
+        };
+
+        let mut clusters = Vec::with_capacity(self.clusters.len());
+        for (&leader_idx, stats) in &self.clusters {
+            clusters.push(ClusterRecord {
+                leader_id: self.id_registry.get_id(stats.best_read_idx).to_string(),
+                member_ids: member_ids_by_leader.remove(&leader_idx).unwrap_or_default(),
+                count: stats.count,
+                best_score: stats.best_score,
+                consensus: consensus_reads.get(&leader_idx).cloned(),
+            });
+        }
+
+        DedupResult { clusters }
+    }
+}
+
+// ============================================================================
+// Rich Results
+// ============================================================================
+
+/// One cluster's full record, as produced by [`DedupContext::finalize_rich`].
+#[derive(Debug, Clone)]
+pub struct ClusterRecord {
+    /// ID of the cluster's best-scoring exemplar (see [`ClusterStats`]).
+    pub leader_id: String,
+    /// IDs of every read that was folded into this cluster, including the leader.
+    pub member_ids: Vec<String>,
+    pub count: usize,
+    pub best_score: f64,
+    /// Present only when consensus accumulation was enabled (see
+    /// [`DedupContext::enable_consensus`]).
+    pub consensus: Option<ReadPair>,
+}
+
+/// Rich deduplication output: one [`ClusterRecord`] per cluster.
+pub struct DedupResult {
+    pub clusters: Vec<ClusterRecord>,
+}
+
+impl DedupResult {
+    /// Flattened `read_id -> leader_id` view, matching
+    /// [`deduplicate_read_pairs`]'s original return shape for callers that
+    /// don't need the rest of [`ClusterRecord`].
+    pub fn read_id_to_cluster_id(&self) -> AHashMap<String, String> {
+        let mut map = AHashMap::new();
+        for cluster in &self.clusters {
+            for member_id in &cluster.member_ids {
+                map.insert(member_id.clone(), cluster.leader_id.clone());
+            }
+        }
+        map
+    }
 }
 
 // ============================================================================
@@ -556,3 +1399,102 @@ pub fn deduplicate_read_pairs(
 
     result_map
 }
+
+// ============================================================================
+// UNIT TESTS
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // -------------------------------------------------------------------------
+    // merge_pair tests
+    // -------------------------------------------------------------------------
+
+    fn qual_of(len: usize) -> String {
+        "I".repeat(len)
+    }
+
+    /// Builds a `ReadPair` whose mates share a known 15bp overlap:
+    /// `fwd_seq` is `flank_fwd` (15bp) + `overlap` (15bp), and `rev_seq` is
+    /// chosen so that reverse-complementing it reproduces `overlap` (15bp) +
+    /// `flank_rev` (15bp). `overlap`'s only shorter self-match (the 7bp
+    /// `TTGGCCA` it repeats) is below `MIN_MERGE_OVERLAP`, so no other
+    /// scanned candidate overlap length coincidentally scores as well.
+    fn overlapping_pair() -> ReadPair {
+        let flank_fwd = "ACGTACGTACGTACG";
+        let overlap = "TTGGCCAATTGGCCA";
+        let flank_rev = "GATCGATCGATCGAT";
+
+        let fwd_seq = format!("{}{}", flank_fwd, overlap);
+        let rc_rev_target = format!("{}{}", overlap, flank_rev);
+        let rev_seq = reverse_complement(&rc_rev_target);
+
+        ReadPair {
+            read_id: "pair".to_string(),
+            fwd_qual: qual_of(fwd_seq.len()),
+            rev_qual: qual_of(rev_seq.len()),
+            fwd_seq,
+            rev_seq,
+        }
+    }
+
+    #[test]
+    fn merge_pair_finds_overlap_with_explicit_insert_size() {
+        let rp = overlapping_pair();
+        // insert_size = fwd_len + rev_len - overlap_len = 30 + 30 - 15 = 45
+        let merged = merge_pair(&rp, Some(45), 0).expect("expected a merge");
+        assert_eq!(merged.overlap_len, 15);
+        assert_eq!(merged.seq.len(), rp.fwd_seq.len() + rp.rev_seq.len() - 15);
+        assert_eq!(&merged.seq[..15], "ACGTACGTACGTACG");
+        assert_eq!(&merged.seq[30..], "GATCGATCGATCGAT");
+    }
+
+    #[test]
+    fn merge_pair_auto_scan_finds_same_overlap() {
+        let rp = overlapping_pair();
+        let merged = merge_pair(&rp, None, 0).expect("expected a merge");
+        assert_eq!(merged.overlap_len, 15);
+        assert_eq!(&merged.seq[..15], "ACGTACGTACGTACG");
+        assert_eq!(&merged.seq[30..], "GATCGATCGATCGAT");
+    }
+
+    #[test]
+    fn merge_pair_auto_scan_ignores_spurious_overlap_below_floor() {
+        // `rev_seq` is built so its only *exact* match against `fwd_seq` is a
+        // coincidental 3bp overlap, well below `MIN_MERGE_OVERLAP`. The
+        // floor keeps the scan from ever considering it, so the best
+        // candidate actually scanned (10bp, the floor) has a real Hamming
+        // distance of 7 and the merge is rejected by the `max_hamming` gate.
+        let fwd_seq = "A".repeat(30);
+        let rc_rev_target = format!("AAA{}", "C".repeat(27));
+        let rev_seq = reverse_complement(&rc_rev_target);
+
+        let rp = ReadPair {
+            read_id: "pair".to_string(),
+            fwd_qual: qual_of(fwd_seq.len()),
+            rev_qual: qual_of(rev_seq.len()),
+            fwd_seq,
+            rev_seq,
+        };
+
+        assert!(merge_pair(&rp, None, 5).is_none());
+    }
+
+    #[test]
+    fn merge_pair_rejects_on_hamming_gate() {
+        let base = overlapping_pair();
+        // Mutate two bases of the overlap copy that ends up in rev_seq, so
+        // the real 15bp overlap now carries 2 mismatches against fwd_seq.
+        let mutated_overlap = "AAGGCCAATTGGCCA";
+        let flank_rev = "GATCGATCGATCGAT";
+        let rc_rev_target = format!("{}{}", mutated_overlap, flank_rev);
+        let rev_seq = reverse_complement(&rc_rev_target);
+
+        let rp = ReadPair { rev_seq, rev_qual: qual_of(rc_rev_target.len()), ..base };
+
+        assert!(merge_pair(&rp, Some(45), 1).is_none());
+        assert!(merge_pair(&rp, Some(45), 2).is_some());
+    }
+}
@@ -1,25 +1,45 @@
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 
-use nao_dedup::{deduplicate_read_pairs, DedupParams, MinimizerParams, ReadPair};
+use nao_dedup::{
+    deduplicate_read_pairs, ClusterRecord, DedupContext, DedupParams, MinimizerParams, ReadPair,
+};
 
 /// Helper to extract a field from a Python ReadPair object
 fn extract_string(obj: &Bound<'_, PyAny>, field: &str) -> PyResult<String> {
     obj.getattr(field)?.extract()
 }
 
+/// Helper to extract an optional field from a Python object, treating a
+/// missing attribute, or one that doesn't extract as a string (e.g. a
+/// Python `None`), as `None`.
+fn extract_optional_string(obj: &Bound<'_, PyAny>, field: &str) -> Option<String> {
+    obj.getattr(field).ok().and_then(|value| value.extract().ok())
+}
+
 /// Convert Python ReadPair to Rust ReadPair
 fn py_to_rust_read_pair(py_rp: &Bound<'_, PyAny>) -> PyResult<ReadPair> {
     let read_id = extract_string(py_rp, "read_id")?;
     let fwd_seq = extract_string(py_rp, "fwd_seq")?;
     let rev_seq = extract_string(py_rp, "rev_seq")?;
 
-    // Python ReadPair stores mean_q but not individual quality strings
-    // Generate dummy quality strings that match the mean quality
-    let mean_q: f64 = py_rp.getattr("mean_q")?.extract()?;
-    let qual_char = ((mean_q.round() as u32) + 33) as u8 as char;
-    let fwd_qual = qual_char.to_string().repeat(fwd_seq.len());
-    let rev_qual = qual_char.to_string().repeat(rev_seq.len());
+    // Prefer the real per-base quality strings when the Python object
+    // carries them. Older Python ReadPairs only store mean_q, so fall back
+    // to a dummy quality string matching that mean for those.
+    let (fwd_qual, rev_qual) = match (
+        extract_optional_string(py_rp, "fwd_qual"),
+        extract_optional_string(py_rp, "rev_qual"),
+    ) {
+        (Some(fwd_qual), Some(rev_qual)) => (fwd_qual, rev_qual),
+        _ => {
+            let mean_q: f64 = py_rp.getattr("mean_q")?.extract()?;
+            let qual_char = ((mean_q.round() as u32) + 33) as u8 as char;
+            (
+                qual_char.to_string().repeat(fwd_seq.len()),
+                qual_char.to_string().repeat(rev_seq.len()),
+            )
+        }
+    };
 
     Ok(ReadPair {
         read_id,
@@ -30,6 +50,56 @@ fn py_to_rust_read_pair(py_rp: &Bound<'_, PyAny>) -> PyResult<ReadPair> {
     })
 }
 
+/// Extract `DedupParams` from a Python params object, if provided.
+fn extract_dedup_params(params: Option<&Bound<'_, PyAny>>) -> PyResult<Option<DedupParams>> {
+    let Some(params) = params else {
+        return Ok(None);
+    };
+    let max_offset: usize = params.getattr("max_offset")?.extract()?;
+    let max_error_frac: f64 = params.getattr("max_error_frac")?.extract()?;
+    Ok(Some(DedupParams { max_offset, max_error_frac, ..Default::default() }))
+}
+
+/// Extract `MinimizerParams` from a Python params object, if provided.
+fn extract_minimizer_params(params: Option<&Bound<'_, PyAny>>) -> PyResult<Option<MinimizerParams>> {
+    let Some(params) = params else {
+        return Ok(None);
+    };
+    let kmer_len: usize = params.getattr("kmer_len")?.extract()?;
+    let window_len: usize = params.getattr("window_len")?.extract()?;
+    let num_windows: usize = params.getattr("num_windows")?.extract()?;
+    Ok(Some(
+        MinimizerParams::new(kmer_len, window_len, num_windows)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?,
+    ))
+}
+
+/// Convert a Rust `ClusterRecord` to a Python dict with keys `leader_id`,
+/// `member_ids`, `count`, `best_score`, and `consensus` (a dict with
+/// `read_id`/`fwd_seq`/`rev_seq`/`fwd_qual`/`rev_qual`, or `None`).
+fn cluster_record_to_pydict<'py>(py: Python<'py>, record: &ClusterRecord) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("leader_id", &record.leader_id)?;
+    dict.set_item("member_ids", record.member_ids.clone())?;
+    dict.set_item("count", record.count)?;
+    dict.set_item("best_score", record.best_score)?;
+
+    match &record.consensus {
+        Some(consensus) => {
+            let consensus_dict = PyDict::new_bound(py);
+            consensus_dict.set_item("read_id", &consensus.read_id)?;
+            consensus_dict.set_item("fwd_seq", &consensus.fwd_seq)?;
+            consensus_dict.set_item("rev_seq", &consensus.rev_seq)?;
+            consensus_dict.set_item("fwd_qual", &consensus.fwd_qual)?;
+            consensus_dict.set_item("rev_qual", &consensus.rev_qual)?;
+            dict.set_item("consensus", consensus_dict)?;
+        }
+        None => dict.set_item("consensus", py.None())?,
+    }
+
+    Ok(dict)
+}
+
 /// Deduplicate read pairs using Rust implementation
 #[pyfunction]
 #[pyo3(signature = (read_pairs, dedup_params=None, minimizer_params=None, verbose=false))]
@@ -46,30 +116,8 @@ fn deduplicate_read_pairs_rust(
         rust_read_pairs.push(py_to_rust_read_pair(&py_rp)?);
     }
 
-    // Extract dedup parameters if provided
-    let rust_dedup_params = if let Some(params) = dedup_params {
-        let max_offset: usize = params.getattr("max_offset")?.extract()?;
-        let max_error_frac: f64 = params.getattr("max_error_frac")?.extract()?;
-        Some(DedupParams {
-            max_offset,
-            max_error_frac,
-        })
-    } else {
-        None
-    };
-
-    // Extract minimizer parameters if provided
-    let rust_minimizer_params = if let Some(params) = minimizer_params {
-        let kmer_len: usize = params.getattr("kmer_len")?.extract()?;
-        let window_len: usize = params.getattr("window_len")?.extract()?;
-        let num_windows: usize = params.getattr("num_windows")?.extract()?;
-        Some(
-            MinimizerParams::new(kmer_len, window_len, num_windows)
-                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e))?
-        )
-    } else {
-        None
-    };
+    let rust_dedup_params = extract_dedup_params(dedup_params)?;
+    let rust_minimizer_params = extract_minimizer_params(minimizer_params)?;
 
     if verbose {
         eprintln!(
@@ -90,9 +138,57 @@ fn deduplicate_read_pairs_rust(
     Ok(py_dict.unbind())
 }
 
+/// Deduplicate read pairs using the Rust implementation, returning one
+/// structured record per cluster (leader id, member ids, count, best score,
+/// and - when `consensus=True` - a consensus read) instead of the flattened
+/// `read_id -> leader_id` map `deduplicate_read_pairs_rust` returns.
+#[pyfunction]
+#[pyo3(signature = (read_pairs, dedup_params=None, minimizer_params=None, consensus=false, verbose=false))]
+fn deduplicate_read_pairs_rich_rust(
+    py: Python<'_>,
+    read_pairs: &Bound<'_, PyList>,
+    dedup_params: Option<&Bound<'_, PyAny>>,
+    minimizer_params: Option<&Bound<'_, PyAny>>,
+    consensus: bool,
+    verbose: bool,
+) -> PyResult<Py<PyList>> {
+    // Convert Python ReadPairs to Rust ReadPairs
+    let mut rust_read_pairs = Vec::new();
+    for py_rp in read_pairs.iter() {
+        rust_read_pairs.push(py_to_rust_read_pair(&py_rp)?);
+    }
+
+    let rust_dedup_params = extract_dedup_params(dedup_params)?.unwrap_or_default();
+    let rust_minimizer_params = extract_minimizer_params(minimizer_params)?.unwrap_or_default();
+
+    if verbose {
+        eprintln!(
+            "Rust deduplication: processing {} read pairs",
+            rust_read_pairs.len()
+        );
+    }
+
+    let mut ctx = DedupContext::new(rust_dedup_params, rust_minimizer_params);
+    if consensus {
+        ctx.enable_consensus();
+    }
+    for rp in rust_read_pairs {
+        ctx.process_read(rp);
+    }
+    let result = ctx.finalize_rich();
+
+    let py_clusters = PyList::empty_bound(py);
+    for cluster in &result.clusters {
+        py_clusters.append(cluster_record_to_pydict(py, cluster)?)?;
+    }
+
+    Ok(py_clusters.unbind())
+}
+
 /// Python module definition
 #[pymodule]
 fn nao_dedup_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(deduplicate_read_pairs_rust, m)?)?;
+    m.add_function(wrap_pyfunction!(deduplicate_read_pairs_rich_rust, m)?)?;
     Ok(())
 }